@@ -93,8 +93,8 @@ mod tests {
         );
     }
 
-    /// This crate does not allow to emulate any input to egui, but it can
-    /// modify the application state and check that the result renders
+    /// Instead of simulating clicks on the button, the test can also modify
+    /// the application state directly and check that the result renders
     /// correctly and that the number is truncated.
     #[test]
     fn test_large_number() {
@@ -0,0 +1,42 @@
+//! Convert the results of the screenshot tests into a JUnit XML file.
+//!
+//! ```plain
+//! egui-screenshot-junit RESULTS [OUTPUT]
+//! ```
+//!
+//! `RESULTS` is either a results file written by the tests when the
+//! environment variable `EGUI_SCREENSHOT_RESULTS` is set, or a report
+//! directory written when `EGUI_SCREENSHOT_REPORT_DIR` is set. The XML file is
+//! written to `OUTPUT`, which defaults to `junit.xml`.
+use std::process::ExitCode;
+
+use egui_screenshot_testing::write_junit_report;
+
+const USAGE: &str = "Usage: egui-screenshot-junit RESULTS [OUTPUT]";
+
+fn main() -> ExitCode {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let (results, output) = match args.as_slice() {
+        [arg] if arg == "-h" || arg == "--help" => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        [results] => (results.as_str(), "junit.xml"),
+        [results, output] => (results.as_str(), output.as_str()),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match write_junit_report(results, output) {
+        Ok(()) => {
+            println!("JUnit report written to {}", output);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! List or delete snapshot files that no test referenced.
+//!
+//! ```plain
+//! egui-screenshot-orphans [--delete] REFERENCES_FILE DIR...
+//! egui-screenshot-orphans --reset REFERENCES_FILE
+//! ```
+//!
+//! The references file is written by the tests when the environment variable
+//! `EGUI_SCREENSHOT_REFERENCES` is set. The tests only append to it, so reset
+//! it with `--reset` before running them. All given snapshot directories are
+//! searched recursively for snapshot files that are not contained in the
+//! references file. Files outside of these directories are never deleted.
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use egui_screenshot_testing::{
+    find_orphaned_snapshots, read_references, reset_references, ScreenshotError,
+};
+
+const USAGE: &str = "Usage: egui-screenshot-orphans [--delete] REFERENCES_FILE DIR...
+       egui-screenshot-orphans --reset REFERENCES_FILE";
+
+fn main() -> ExitCode {
+    let mut delete = false;
+    let mut reset = false;
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--delete" => delete = true,
+            "--reset" => reset = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("Unknown option {}\n{}", arg, USAGE);
+                return ExitCode::FAILURE;
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+    let references_file = paths.remove(0);
+    let result = if reset {
+        if delete || !paths.is_empty() {
+            eprintln!("--reset only takes the references file\n{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+        reset_references(&references_file)
+    } else {
+        if paths.is_empty() {
+            eprintln!("No snapshot directory given\n{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+        orphans(&references_file, &paths, delete)
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn orphans(references_file: &Path, dirs: &[PathBuf], delete: bool) -> Result<(), ScreenshotError> {
+    let references = read_references(references_file)?;
+    if references.is_empty() {
+        // Deleting would remove all snapshots, which is most likely a mistake
+        println!(
+            "No snapshot files are referenced in {}, run the tests with \
+             EGUI_SCREENSHOT_REFERENCES first",
+            references_file.display()
+        );
+        return Ok(());
+    }
+
+    let mut count = 0;
+    for dir in dirs {
+        for file in find_orphaned_snapshots(dir, &references)? {
+            if delete {
+                std::fs::remove_file(&file).map_err(|e| ScreenshotError::Io {
+                    path: file.clone(),
+                    source: e,
+                })?;
+                println!("Deleted {}", file.display());
+            } else {
+                println!("{}", file.display());
+            }
+            count += 1;
+        }
+    }
+    if count == 0 {
+        println!("No orphaned snapshot files found");
+    }
+    Ok(())
+}
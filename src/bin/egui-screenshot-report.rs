@@ -0,0 +1,40 @@
+//! Create an HTML page from the results in a report directory.
+//!
+//! ```plain
+//! egui-screenshot-report REPORT_DIR
+//! ```
+//!
+//! The results are written by the tests when the environment variable
+//! `EGUI_SCREENSHOT_REPORT_DIR` is set. The page is written to
+//! `REPORT_DIR/index.html`.
+use std::process::ExitCode;
+
+use egui_screenshot_testing::write_html_report;
+
+const USAGE: &str = "Usage: egui-screenshot-report REPORT_DIR";
+
+fn main() -> ExitCode {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let report_dir = match args.as_slice() {
+        [arg] if arg == "-h" || arg == "--help" => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        [report_dir] => report_dir,
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match write_html_report(report_dir) {
+        Ok(html_file) => {
+            println!("Report written to {}", html_file.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
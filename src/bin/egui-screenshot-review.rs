@@ -0,0 +1,134 @@
+//! Review pending snapshots (`*.new.png` files) and accept or reject them.
+//!
+//! ```plain
+//! egui-screenshot-review [--accept-all | --reject-all] DIR...
+//! ```
+//!
+//! The given snapshot directories are searched recursively. A `*.new.png`
+//! file is only a pending snapshot if the snapshot it replaces exists or if
+//! it was written by the `pending` update policy. Without any option, each
+//! pending snapshot is shown and the decision is read from the terminal.
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use egui_screenshot_testing::{find_pending_snapshots, PendingSnapshot, ScreenshotError};
+
+const USAGE: &str = "Usage: egui-screenshot-review [--accept-all | --reject-all] DIR...";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Interactive,
+    AcceptAll,
+    RejectAll,
+}
+
+fn main() -> ExitCode {
+    let mut mode = Mode::Interactive;
+    let mut dirs = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--accept-all" => mode = Mode::AcceptAll,
+            "--reject-all" => mode = Mode::RejectAll,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("Unknown option {}\n{}", arg, USAGE);
+                return ExitCode::FAILURE;
+            }
+            _ => dirs.push(PathBuf::from(arg)),
+        }
+    }
+    if dirs.is_empty() {
+        eprintln!("No snapshot directory given\n{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+
+    match review(&dirs, mode) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn review(dirs: &[PathBuf], mode: Mode) -> Result<(), ScreenshotError> {
+    let mut pending = Vec::new();
+    for dir in dirs {
+        pending.extend(find_pending_snapshots(dir)?);
+    }
+    if pending.is_empty() {
+        println!("No pending snapshots found");
+        return Ok(());
+    }
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    let (mut accepted, mut rejected) = (0, 0);
+    for (idx, snapshot) in pending.iter().enumerate() {
+        println!(
+            "[{}/{}] {}",
+            idx + 1,
+            pending.len(),
+            snapshot.expected_file.display()
+        );
+        println!("  {}", describe(snapshot)?);
+
+        let decision = match mode {
+            Mode::AcceptAll => 'a',
+            Mode::RejectAll => 'r',
+            Mode::Interactive => loop {
+                print!("  Accept (a), reject (r), skip (s) or quit (q)? ");
+                std::io::stdout().flush().ok();
+                let line = match lines.next() {
+                    Some(line) => line.map_err(|e| ScreenshotError::Io {
+                        path: PathBuf::from("<stdin>"),
+                        source: e,
+                    })?,
+                    None => String::from("q"),
+                };
+                match line.trim() {
+                    "a" | "r" | "s" | "q" => break line.trim().chars().next().unwrap_or('q'),
+                    _ => continue,
+                }
+            },
+        };
+        match decision {
+            'a' => {
+                snapshot.accept()?;
+                accepted += 1;
+            }
+            'r' => {
+                snapshot.reject()?;
+                rejected += 1;
+            }
+            'q' => break,
+            _ => {}
+        }
+    }
+    println!(
+        "{} accepted, {} rejected, {} still pending",
+        accepted,
+        rejected,
+        pending.len() - accepted - rejected
+    );
+    Ok(())
+}
+
+/// A short description of how the pending snapshot differs from the
+/// existing one.
+fn describe(snapshot: &PendingSnapshot) -> Result<String, ScreenshotError> {
+    if snapshot.is_new() {
+        return Ok(format!("new snapshot {}", snapshot.pending_file.display()));
+    }
+    Ok(match snapshot.mismatch()? {
+        Some(mismatch) => format!("{} ({})", snapshot.pending_file.display(), mismatch),
+        None => format!(
+            "{} (same pixels as the existing snapshot)",
+            snapshot.pending_file.display()
+        ),
+    })
+}
@@ -0,0 +1,225 @@
+//! Configure all settings of a [`TestBackend`] in one place.
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use egui::{Color32, Modifiers};
+
+use crate::{clock, config, create_egui_backend, Comparison, FontSetup, TestBackend, UpdatePolicy};
+
+/// Default directory of the snapshot files.
+pub(crate) const DEFAULT_EXPECTED_DIR: &str = "src/tests/expected";
+/// Default directory the actual screenshots of failed tests are written to.
+pub(crate) const DEFAULT_ACTUAL_DIR: &str = "src/tests/actual";
+/// Default output size in egui points for [`TestBackend::assert_screenshot`].
+pub(crate) const DEFAULT_SIZE: (i32, i32) = (800, 600);
+/// Default number of frames rendered by [`TestBackend::assert_screenshot`].
+pub(crate) const DEFAULT_FRAMES: usize = 5;
+
+/// Builder for a [`TestBackend`], created with [`TestBackend::builder`].
+///
+/// Settings that are not given are taken from the configuration file or use
+/// the defaults. [`TestBackendBuilder::default`] creates a builder that
+/// ignores the configuration file.
+#[derive(Clone, Debug)]
+pub struct TestBackendBuilder {
+    expected_dir: PathBuf,
+    actual_dir: PathBuf,
+    comparison: Comparison,
+    pixels_per_point: f32,
+    default_size: (i32, i32),
+    frames: usize,
+    frame_dt: Option<Duration>,
+    update_policy: UpdatePolicy,
+    fonts: Option<FontSetup>,
+    background: Option<Color32>,
+    report_dir: Option<PathBuf>,
+    results_file: Option<PathBuf>,
+    references_file: Option<PathBuf>,
+}
+
+impl Default for TestBackendBuilder {
+    fn default() -> Self {
+        TestBackendBuilder {
+            expected_dir: PathBuf::from(DEFAULT_EXPECTED_DIR),
+            actual_dir: PathBuf::from(DEFAULT_ACTUAL_DIR),
+            comparison: Comparison::default(),
+            pixels_per_point: 1.0,
+            default_size: DEFAULT_SIZE,
+            frames: DEFAULT_FRAMES,
+            frame_dt: None,
+            update_policy: UpdatePolicy::default(),
+            fonts: None,
+            background: None,
+            report_dir: None,
+            results_file: None,
+            references_file: None,
+        }
+    }
+}
+
+impl TestBackendBuilder {
+    /// Directory in which the snapshot files are located. The default is
+    /// `src/tests/expected`.
+    pub fn expected_dir(mut self, expected_dir: impl Into<PathBuf>) -> Self {
+        self.expected_dir = expected_dir.into();
+        self
+    }
+
+    /// Directory the actual screenshots of failed comparisons are written to.
+    /// The default is `src/tests/actual`.
+    pub fn actual_dir(mut self, actual_dir: impl Into<PathBuf>) -> Self {
+        self.actual_dir = actual_dir.into();
+        self
+    }
+
+    /// See [`TestBackend::with_comparison`].
+    pub fn comparison(mut self, comparison: Comparison) -> Self {
+        self.comparison = comparison;
+        self
+    }
+
+    /// See [`TestBackend::with_pixels_per_point`].
+    pub fn pixels_per_point(mut self, pixels_per_point: f32) -> Self {
+        self.pixels_per_point = pixels_per_point;
+        self
+    }
+
+    /// The output size in egui points used by [`TestBackend::assert_screenshot`].
+    /// The default is 800x600.
+    pub fn default_size(mut self, size: (i32, i32)) -> Self {
+        self.default_size = size;
+        self
+    }
+
+    /// The number of frames rendered by [`TestBackend::assert_screenshot`]
+    /// before the screenshot is compared. The default is 5.
+    pub fn frames(mut self, n: usize) -> Self {
+        self.frames = n;
+        self
+    }
+
+    /// See [`TestBackend::with_frame_dt`].
+    pub fn frame_dt(mut self, frame_dt: Duration) -> Self {
+        self.frame_dt = Some(frame_dt);
+        self
+    }
+
+    /// See [`TestBackend::with_update_policy`].
+    pub fn update_policy(mut self, update_policy: UpdatePolicy) -> Self {
+        self.update_policy = update_policy;
+        self
+    }
+
+    /// See [`TestBackend::with_fonts`].
+    pub fn fonts(mut self, fonts: FontSetup) -> Self {
+        self.fonts = Some(fonts);
+        self
+    }
+
+    /// See [`TestBackend::with_background`].
+    pub fn background(mut self, color: Color32) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    /// See [`TestBackend::with_report_dir`].
+    pub fn report_dir(mut self, report_dir: impl Into<PathBuf>) -> Self {
+        self.report_dir = Some(report_dir.into());
+        self
+    }
+
+    /// See [`TestBackend::with_results_file`].
+    pub fn results_file(mut self, results_file: impl Into<PathBuf>) -> Self {
+        self.results_file = Some(results_file.into());
+        self
+    }
+
+    /// See [`TestBackend::with_references_file`].
+    pub fn references_file(mut self, references_file: impl Into<PathBuf>) -> Self {
+        self.references_file = Some(references_file.into());
+        self
+    }
+
+    /// Create the backend. The `init_app_with_context` closure is executed
+    /// once to init the application, before the pinned fonts are applied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the frame duration is zero or the scale factor is not a
+    /// positive number.
+    pub fn build(self, init_app_with_context: impl FnOnce(&egui::Context)) -> TestBackend {
+        let backend = create_egui_backend();
+        init_app_with_context(&backend.egui_ctx);
+        let test_backend = TestBackend {
+            backend,
+            expected_dir: self.expected_dir,
+            actual_dir: self.actual_dir,
+            pending_input: VecDeque::new(),
+            modifiers: Modifiers::NONE,
+            accesskit_tree: None,
+            comparison: self.comparison,
+            pixels_per_point: 1.0,
+            time: 0.0,
+            frame_dt: clock::DEFAULT_FRAME_DT,
+            masks: Vec::new(),
+            font_fingerprint: None,
+            frames_since_screenshot: 0,
+            update_policy: self.update_policy,
+            references_file: self.references_file,
+            touched_snapshots: Vec::new(),
+            report_dir: self.report_dir,
+            results_file: self.results_file,
+            last_screenshot: Instant::now(),
+            default_size: self.default_size,
+            default_frames: self.frames,
+            background: self.background,
+        };
+        let test_backend = test_backend.with_pixels_per_point(self.pixels_per_point);
+        let test_backend = match self.frame_dt {
+            Some(frame_dt) => test_backend.with_frame_dt(frame_dt),
+            None => test_backend,
+        };
+        match self.fonts {
+            Some(fonts) => test_backend.with_fonts(fonts),
+            None => test_backend,
+        }
+    }
+}
+
+impl TestBackend {
+    /// Create a [`TestBackendBuilder`] to configure all settings of the
+    /// backend in one place.
+    ///
+    /// The builder starts with the settings of the configuration file
+    /// `egui-screenshot.toml` in the directory of the crate (given by
+    /// `CARGO_MANIFEST_DIR`) or the file given by the environment variable
+    /// `EGUI_SCREENSHOT_CONFIG`. Settings given to the builder override the
+    /// ones of the configuration file, and environment variables like
+    /// `EGUI_SCREENSHOT_UPDATE` override both.
+    ///
+    /// ```no_run
+    /// use egui_screenshot_testing::{Comparison, TestBackend};
+    ///
+    /// let mut backend = TestBackend::builder()
+    ///     .expected_dir("tests/expected")
+    ///     .comparison(Comparison::Exact)
+    ///     .default_size((300, 200))
+    ///     .build(|_ctx| {});
+    /// backend.assert_screenshot("main.png", |ctx| {
+    ///     egui::CentralPanel::default().show(ctx, |ui| {
+    ///         ui.heading("Hello World");
+    ///     });
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration file can not be read or is invalid.
+    pub fn builder() -> TestBackendBuilder {
+        match config::load_builder() {
+            Ok(builder) => builder,
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
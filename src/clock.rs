@@ -0,0 +1,66 @@
+//! A virtual clock that makes animations deterministic.
+//!
+//! Each rendered frame advances the time by a fixed duration, independent of
+//! how long rendering the frame took.
+use std::time::Duration;
+
+use crate::TestBackend;
+
+/// Default duration of a single frame, which is the same egui assumes.
+pub(crate) const DEFAULT_FRAME_DT: f64 = 1.0 / 60.0;
+
+impl TestBackend {
+    /// Set the duration a single rendered frame advances the virtual clock.
+    /// The default is 1/60 of a second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame_dt` is zero.
+    pub fn with_frame_dt(mut self, frame_dt: Duration) -> Self {
+        assert!(!frame_dt.is_zero(), "The frame duration must not be zero");
+        self.frame_dt = frame_dt.as_secs_f64();
+        self
+    }
+
+    /// The current time of the virtual clock, measured since the backend
+    /// has been created.
+    pub fn time(&self) -> Duration {
+        Duration::from_secs_f64(self.time)
+    }
+
+    /// Jump forward in time without rendering any frames. The next rendered
+    /// frame will see the new time, e.g. to continue an animation at a later
+    /// point.
+    pub fn advance_time(&mut self, duration: Duration) {
+        self.time += duration.as_secs_f64();
+    }
+
+    /// Render frames until the virtual clock has advanced by at least the
+    /// given `duration`.
+    ///
+    /// * `output_size` - The dimensions of the rendered view in egui points.
+    /// * `duration` - How much time should pass.
+    /// * `ui` - Closure that creates the user interface.
+    ///
+    /// This is useful to take a screenshot at a specific point of an
+    /// animation, e.g. when a collapsing header is halfway open.
+    pub fn run_for(
+        &mut self,
+        output_size: (i32, i32),
+        duration: Duration,
+        ui: impl FnMut(&egui::Context),
+    ) {
+        // Ignore small rounding errors, so e.g. 0.5 seconds are exactly 30 frames
+        let frames = duration.as_secs_f64() / self.frame_dt;
+        let n = (frames - 1e-6).ceil().max(0.0) as usize;
+        self.run_frames(output_size, n, ui);
+    }
+
+    /// Set the time of the given frame input and advance the clock by one
+    /// frame.
+    pub(crate) fn tick(&mut self, input: &mut egui::RawInput) {
+        input.time = Some(self.time);
+        input.predicted_dt = self.frame_dt as f32;
+        self.time += self.frame_dt;
+    }
+}
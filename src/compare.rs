@@ -0,0 +1,287 @@
+//! Strategies to compare the actual screenshot with the expected snapshot.
+use std::fmt::Display;
+
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use visual_hash::HasherConfig;
+
+/// Limits how many pixels may differ when using [`Comparison::Tolerance`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PixelLimit {
+    /// Absolute number of pixels that may differ.
+    Count(usize),
+    /// Percentage (between 0.0 and 100.0) of all pixels that may differ.
+    Percentage(f32),
+}
+
+impl PixelLimit {
+    fn allows(&self, differing_pixels: usize, total_pixels: usize) -> bool {
+        match self {
+            PixelLimit::Count(count) => differing_pixels <= *count,
+            PixelLimit::Percentage(percentage) => {
+                total_pixels == 0
+                    || (differing_pixels as f64 / total_pixels as f64) * 100.0 <= *percentage as f64
+            }
+        }
+    }
+}
+
+/// How the actual screenshot is compared to the expected snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum Comparison {
+    /// All pixels must be exactly the same.
+    Exact,
+    /// A pixel is only counted as different if one of its color channels
+    /// differs by more than `channel_tolerance`. The comparison fails if more
+    /// pixels differ than allowed by `max_differing_pixels`.
+    Tolerance {
+        channel_tolerance: u8,
+        max_differing_pixels: PixelLimit,
+    },
+    /// Compare a perceptual hash of both images. The comparison fails if the
+    /// distance of the hashes is larger than `max_distance`.
+    PerceptualHash { max_distance: u32 },
+}
+
+impl Default for Comparison {
+    fn default() -> Self {
+        Comparison::PerceptualHash { max_distance: 0 }
+    }
+}
+
+/// Statistics about the difference of two images.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiffStats {
+    /// Number of pixels that differ more than the allowed tolerance.
+    pub differing_pixels: usize,
+    /// Number of pixels in the image.
+    pub total_pixels: usize,
+    /// The largest difference of a single color channel.
+    pub max_channel_difference: u8,
+    /// Distance of the perceptual hashes, only set when using
+    /// [`Comparison::PerceptualHash`].
+    pub hash_distance: Option<u32>,
+}
+
+impl Display for DiffStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} pixels differ, maximum channel difference is {}",
+            self.differing_pixels, self.total_pixels, self.max_channel_difference
+        )?;
+        if let Some(hash_distance) = self.hash_distance {
+            write!(f, ", perceptual hash distance is {}", hash_distance)?;
+        }
+        Ok(())
+    }
+}
+
+/// The reason why two images are not considered equal.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mismatch {
+    /// The images have different dimensions.
+    Size {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// The images differ more than allowed.
+    Pixels(DiffStats),
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::Size { expected, actual } => write!(
+                f,
+                "expected size {}x{} but actual size is {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            Mismatch::Pixels(stats) => stats.fmt(f),
+        }
+    }
+}
+
+impl Comparison {
+    /// Compare the `actual` image with the `expected` one. Images with
+    /// different dimensions never match, regardless of the comparison.
+    pub fn compare(&self, expected: &DynamicImage, actual: &DynamicImage) -> Result<(), Mismatch> {
+        if expected.width() != actual.width() || expected.height() != actual.height() {
+            return Err(Mismatch::Size {
+                expected: (expected.width(), expected.height()),
+                actual: (actual.width(), actual.height()),
+            });
+        }
+        match self {
+            Comparison::Exact => compare_pixels(expected, actual, 0, PixelLimit::Count(0)),
+            Comparison::Tolerance {
+                channel_tolerance,
+                max_differing_pixels,
+            } => compare_pixels(expected, actual, *channel_tolerance, *max_differing_pixels),
+            Comparison::PerceptualHash { max_distance } => {
+                let hasher = HasherConfig::default().to_hasher();
+                let expected_hash = hasher.hash_image(expected);
+                let actual_hash = hasher.hash_image(actual);
+
+                let dist = actual_hash.dist(&expected_hash);
+                if dist <= *max_distance {
+                    Ok(())
+                } else {
+                    let mut stats = pixel_stats(&expected.to_rgba8(), &actual.to_rgba8(), 0);
+                    stats.hash_distance = Some(dist);
+                    Err(Mismatch::Pixels(stats))
+                }
+            }
+        }
+    }
+
+    /// The largest difference of a color channel for which two pixels are
+    /// still considered equal.
+    pub(crate) fn channel_tolerance(&self) -> u8 {
+        match self {
+            Comparison::Tolerance {
+                channel_tolerance, ..
+            } => *channel_tolerance,
+            Comparison::Exact | Comparison::PerceptualHash { .. } => 0,
+        }
+    }
+}
+
+fn compare_pixels(
+    expected: &DynamicImage,
+    actual: &DynamicImage,
+    channel_tolerance: u8,
+    limit: PixelLimit,
+) -> Result<(), Mismatch> {
+    let stats = pixel_stats(&expected.to_rgba8(), &actual.to_rgba8(), channel_tolerance);
+    if limit.allows(stats.differing_pixels, stats.total_pixels) {
+        Ok(())
+    } else {
+        Err(Mismatch::Pixels(stats))
+    }
+}
+
+/// Count the pixels of two images with the same size that differ more than
+/// the `channel_tolerance`.
+fn pixel_stats(expected: &RgbaImage, actual: &RgbaImage, channel_tolerance: u8) -> DiffStats {
+    let mut stats = DiffStats {
+        total_pixels: (expected.width() * expected.height()) as usize,
+        ..Default::default()
+    };
+    for (e, a) in expected.pixels().zip(actual.pixels()) {
+        let difference = channel_difference(e, a);
+        stats.max_channel_difference = stats.max_channel_difference.max(difference);
+        if difference > channel_tolerance {
+            stats.differing_pixels += 1;
+        }
+    }
+    stats
+}
+
+/// The largest difference of any color channel of the two pixels.
+pub(crate) fn channel_difference(a: &image::Rgba<u8>, b: &image::Rgba<u8>) -> u8 {
+    a.0.iter()
+        .zip(b.0.iter())
+        .map(|(a, b)| a.abs_diff(*b))
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    fn image_with_pixel(pixel: Rgba<u8>) -> DynamicImage {
+        let mut image = RgbaImage::from_pixel(10, 10, Rgba([255, 255, 255, 255]));
+        image.put_pixel(5, 5, pixel);
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn exact_detects_single_pixel() {
+        let expected = image_with_pixel(Rgba([255, 255, 255, 255]));
+        let actual = image_with_pixel(Rgba([254, 255, 255, 255]));
+
+        assert_eq!(Ok(()), Comparison::Exact.compare(&expected, &expected));
+        let result = Comparison::Exact.compare(&expected, &actual);
+        assert_eq!(
+            Err(Mismatch::Pixels(DiffStats {
+                differing_pixels: 1,
+                total_pixels: 100,
+                max_channel_difference: 1,
+                hash_distance: None,
+            })),
+            result
+        );
+    }
+
+    #[test]
+    fn tolerance_limits() {
+        let expected = image_with_pixel(Rgba([255, 255, 255, 255]));
+        let actual = image_with_pixel(Rgba([200, 255, 255, 255]));
+
+        let within_channel_tolerance = Comparison::Tolerance {
+            channel_tolerance: 55,
+            max_differing_pixels: PixelLimit::Count(0),
+        };
+        assert_eq!(Ok(()), within_channel_tolerance.compare(&expected, &actual));
+
+        let within_count = Comparison::Tolerance {
+            channel_tolerance: 0,
+            max_differing_pixels: PixelLimit::Count(1),
+        };
+        assert_eq!(Ok(()), within_count.compare(&expected, &actual));
+
+        let within_percentage = Comparison::Tolerance {
+            channel_tolerance: 0,
+            max_differing_pixels: PixelLimit::Percentage(1.0),
+        };
+        assert_eq!(Ok(()), within_percentage.compare(&expected, &actual));
+
+        let too_strict = Comparison::Tolerance {
+            channel_tolerance: 10,
+            max_differing_pixels: PixelLimit::Percentage(0.5),
+        };
+        assert!(too_strict.compare(&expected, &actual).is_err());
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let expected = DynamicImage::ImageRgba8(RgbaImage::new(10, 10));
+        let actual = DynamicImage::ImageRgba8(RgbaImage::new(10, 20));
+        for comparison in [
+            Comparison::Exact,
+            Comparison::Tolerance {
+                channel_tolerance: 255,
+                max_differing_pixels: PixelLimit::Percentage(100.0),
+            },
+            Comparison::PerceptualHash { max_distance: 0 },
+        ] {
+            assert_eq!(
+                Err(Mismatch::Size {
+                    expected: (10, 10),
+                    actual: (10, 20)
+                }),
+                comparison.compare(&expected, &actual)
+            );
+        }
+    }
+
+    #[test]
+    fn comparison_as_json() {
+        let comparison = Comparison::Tolerance {
+            channel_tolerance: 2,
+            max_differing_pixels: PixelLimit::Count(10),
+        };
+        let json = serde_json::to_string(&comparison).unwrap();
+        assert_eq!(
+            r#"{"mode":"tolerance","channel_tolerance":2,"max_differing_pixels":{"count":10}}"#,
+            json
+        );
+        assert_eq!(comparison, serde_json::from_str(&json).unwrap());
+    }
+}
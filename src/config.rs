@@ -0,0 +1,258 @@
+//! Project-wide defaults for all tests, read from a configuration file.
+//!
+//! ```toml
+//! expected_dir = "tests/expected"
+//! actual_dir = "target/screenshots"
+//! pixels_per_point = 2.0
+//! default_size = [300, 200]
+//! frames = 5
+//! update_policy = "missing"
+//! # Either "bundled" or the path of a TTF or OTF file
+//! fonts = "bundled"
+//!
+//! [comparison]
+//! # One of "exact", "tolerance" or "perceptual-hash"
+//! mode = "tolerance"
+//! channel_tolerance = 2
+//! # Either an absolute number or a percentage of the pixels
+//! max_differing_pixels = 10
+//! # max_differing_percentage = 0.5
+//! ```
+//!
+//! Relative paths are resolved against the directory of the configuration
+//! file.
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::{Comparison, FontSetup, PixelLimit, TestBackendBuilder, UpdatePolicy};
+
+/// Name of the configuration file in the directory of the crate.
+pub(crate) const CONFIG_FILE_NAME: &str = "egui-screenshot.toml";
+/// Environment variable with the path of the configuration file, which is
+/// used instead of the file in the directory of the crate.
+pub(crate) const CONFIG_ENV_VAR: &str = "EGUI_SCREENSHOT_CONFIG";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    expected_dir: Option<PathBuf>,
+    actual_dir: Option<PathBuf>,
+    pixels_per_point: Option<f32>,
+    default_size: Option<(i32, i32)>,
+    frames: Option<usize>,
+    update_policy: Option<String>,
+    fonts: Option<String>,
+    comparison: Option<ComparisonConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+enum ComparisonConfig {
+    Exact,
+    Tolerance {
+        #[serde(default)]
+        channel_tolerance: u8,
+        max_differing_pixels: Option<usize>,
+        max_differing_percentage: Option<f32>,
+    },
+    PerceptualHash {
+        #[serde(default)]
+        max_distance: u32,
+    },
+}
+
+impl ComparisonConfig {
+    fn comparison(&self) -> Result<Comparison, String> {
+        Ok(match self {
+            ComparisonConfig::Exact => Comparison::Exact,
+            ComparisonConfig::Tolerance {
+                channel_tolerance,
+                max_differing_pixels,
+                max_differing_percentage,
+            } => {
+                let max_differing_pixels = match (max_differing_pixels, max_differing_percentage) {
+                    (Some(_), Some(_)) => {
+                        return Err("Only one of max_differing_pixels and \
+                                    max_differing_percentage can be set"
+                            .to_string())
+                    }
+                    (_, Some(percentage)) => PixelLimit::Percentage(*percentage),
+                    (count, None) => PixelLimit::Count(count.unwrap_or_default()),
+                };
+                Comparison::Tolerance {
+                    channel_tolerance: *channel_tolerance,
+                    max_differing_pixels,
+                }
+            }
+            ComparisonConfig::PerceptualHash { max_distance } => Comparison::PerceptualHash {
+                max_distance: *max_distance,
+            },
+        })
+    }
+}
+
+impl Config {
+    /// Apply all settings of the configuration to the builder. Relative
+    /// paths are resolved against `base_dir`.
+    fn apply(
+        self,
+        mut builder: TestBackendBuilder,
+        base_dir: &Path,
+    ) -> Result<TestBackendBuilder, String> {
+        if let Some(expected_dir) = self.expected_dir {
+            builder = builder.expected_dir(base_dir.join(expected_dir));
+        }
+        if let Some(actual_dir) = self.actual_dir {
+            builder = builder.actual_dir(base_dir.join(actual_dir));
+        }
+        if let Some(pixels_per_point) = self.pixels_per_point {
+            if !(pixels_per_point.is_finite() && pixels_per_point > 0.0) {
+                return Err("pixels_per_point must be a positive number".to_string());
+            }
+            builder = builder.pixels_per_point(pixels_per_point);
+        }
+        if let Some(default_size) = self.default_size {
+            builder = builder.default_size(default_size);
+        }
+        if let Some(frames) = self.frames {
+            builder = builder.frames(frames);
+        }
+        if let Some(update_policy) = self.update_policy {
+            builder = builder.update_policy(update_policy.parse::<UpdatePolicy>()?);
+        }
+        if let Some(fonts) = self.fonts {
+            builder = builder.fonts(font_setup(&fonts, base_dir)?);
+        }
+        if let Some(comparison) = self.comparison {
+            builder = builder.comparison(comparison.comparison()?);
+        }
+        Ok(builder)
+    }
+}
+
+/// Parse the `fonts` setting, which is either `bundled` or the path of a
+/// font file.
+fn font_setup(fonts: &str, base_dir: &Path) -> Result<FontSetup, String> {
+    if fonts == "bundled" {
+        return Ok(FontSetup::Bundled);
+    }
+    let font_file = base_dir.join(fonts);
+    let font = std::fs::read(&font_file)
+        .map_err(|e| format!("Could not read font file {:#?}: {}", font_file, e))?;
+    let name = font_file
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    Ok(FontSetup::from_font_bytes(name, font))
+}
+
+/// The configuration file given by the environment variable or the one in
+/// the directory of the crate, if it exists.
+fn find_config_file() -> Option<PathBuf> {
+    if let Some(config_file) = std::env::var_os(CONFIG_ENV_VAR) {
+        return Some(PathBuf::from(config_file));
+    }
+    let crate_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .or_else(|| std::env::current_dir().ok())?;
+    let config_file = crate_dir.join(CONFIG_FILE_NAME);
+    config_file.is_file().then_some(config_file)
+}
+
+/// Read the configuration file and apply it to the builder.
+fn apply_config_file(
+    config_file: &Path,
+    builder: TestBackendBuilder,
+) -> Result<TestBackendBuilder, String> {
+    let content = std::fs::read_to_string(config_file).map_err(|e| {
+        format!(
+            "Could not read configuration file {:#?}: {}",
+            config_file, e
+        )
+    })?;
+    let config: Config = toml::from_str(&content)
+        .map_err(|e| format!("Invalid configuration file {:#?}: {}", config_file, e))?;
+    let base_dir = config_file.parent().unwrap_or(Path::new(""));
+    config
+        .apply(builder, base_dir)
+        .map_err(|e| format!("Invalid configuration file {:#?}: {}", config_file, e))
+}
+
+/// Create a builder with the settings of the configuration file, if there is
+/// one.
+pub(crate) fn load_builder() -> Result<TestBackendBuilder, String> {
+    match find_config_file() {
+        Some(config_file) => apply_config_file(&config_file, TestBackendBuilder::default()),
+        None => Ok(TestBackendBuilder::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_config_file_to_builder() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(
+            &config_file,
+            r#"
+expected_dir = "snapshots"
+pixels_per_point = 2.0
+default_size = [300, 200]
+update_policy = "missing"
+
+[comparison]
+mode = "tolerance"
+channel_tolerance = 2
+max_differing_percentage = 0.5
+"#,
+        )
+        .unwrap();
+
+        let builder = apply_config_file(&config_file, TestBackendBuilder::default()).unwrap();
+        // Settings of the builder override the configuration file
+        let backend = builder.pixels_per_point(1.5).build(|_ctx| {});
+        assert_eq!(dir.path().join("snapshots"), backend.expected_dir);
+        assert_eq!(1.5, backend.pixels_per_point);
+        assert_eq!((300, 200), backend.default_size);
+        assert_eq!(UpdatePolicy::Missing, backend.update_policy);
+        assert_eq!(
+            Comparison::Tolerance {
+                channel_tolerance: 2,
+                max_differing_pixels: PixelLimit::Percentage(0.5)
+            },
+            backend.comparison
+        );
+
+        std::fs::write(&config_file, "tolerance = 2").unwrap();
+        assert!(apply_config_file(&config_file, TestBackendBuilder::default()).is_err());
+    }
+
+    #[test]
+    fn find_config_file_in_crate_dir_or_env_var() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join(CONFIG_FILE_NAME);
+        let other_config_file = dir.path().join("other.toml");
+
+        let crate_dir = Some(dir.path().as_os_str());
+        temp_env::with_vars(
+            [("CARGO_MANIFEST_DIR", crate_dir), (CONFIG_ENV_VAR, None)],
+            || {
+                assert_eq!(None, find_config_file());
+                std::fs::write(&config_file, "frames = 2").unwrap();
+                assert_eq!(Some(config_file.clone()), find_config_file());
+
+                // The environment variable takes precedence, even if the file
+                // does not exist
+                temp_env::with_var(CONFIG_ENV_VAR, Some(&other_config_file), || {
+                    assert_eq!(Some(other_config_file.clone()), find_config_file());
+                    assert!(load_builder().is_err());
+                });
+            },
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Create images that visualize the difference between the expected and
+//! actual screenshot.
+use std::path::{Path, PathBuf};
+
+use image::math::Rect as PixelRect;
+use image::{imageops, DynamicImage, ImageResult, Rgba, RgbaImage};
+
+use crate::compare::channel_difference;
+use crate::mask;
+
+/// Color used to mark differing pixels.
+const HIGHLIGHT_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+/// Color of the gap between the images of the side-by-side composite.
+const SEPARATOR_COLOR: Rgba<u8> = Rgba([128, 128, 128, 255]);
+/// Width of the gap between the images of the side-by-side composite.
+const SEPARATOR_WIDTH: u32 = 4;
+
+/// Returns the paths for the highlighted difference image and the
+/// side-by-side composite that belong to the given actual screenshot file.
+pub(crate) fn diff_file_paths(actual_file: &Path) -> (PathBuf, PathBuf) {
+    let stem = actual_file
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    (
+        actual_file.with_file_name(format!("{stem}.diff.png")),
+        actual_file.with_file_name(format!("{stem}.compare.png")),
+    )
+}
+
+/// Write the highlighted difference image and a side-by-side composite of the
+/// expected, difference and actual image next to the actual screenshot file.
+/// Masked regions are tinted in all images, and pixels that differ by at most
+/// `channel_tolerance` are not highlighted.
+pub(crate) fn write_diff_images(
+    expected: &DynamicImage,
+    actual: &DynamicImage,
+    masks: &[PixelRect],
+    channel_tolerance: u8,
+    actual_file: &Path,
+) -> ImageResult<()> {
+    let mut expected = expected.to_rgba8();
+    let mut actual = actual.to_rgba8();
+    let mut diff = highlight_differences(&expected, &actual, channel_tolerance);
+    for image in [&mut expected, &mut actual, &mut diff] {
+        mask::tint_masks(image, masks);
+    }
+    let composite = side_by_side(&[&expected, &diff, &actual]);
+
+    let (diff_file, composite_file) = diff_file_paths(actual_file);
+    diff.save(diff_file)?;
+    composite.save(composite_file)?;
+    Ok(())
+}
+
+/// Remove diff images from a previous failed run.
+pub(crate) fn remove_diff_images(actual_file: &Path) -> std::io::Result<()> {
+    let (diff_file, composite_file) = diff_file_paths(actual_file);
+    for file in [diff_file, composite_file] {
+        if file.is_file() {
+            std::fs::remove_file(file)?;
+        }
+    }
+    Ok(())
+}
+
+/// Create an image where all pixels that differ more than the
+/// `channel_tolerance` are marked with a highlight color. Other pixels are
+/// shown faded, so the changes can be located in the user interface.
+pub(crate) fn highlight_differences(
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    channel_tolerance: u8,
+) -> RgbaImage {
+    let width = expected.width().max(actual.width());
+    let height = expected.height().max(actual.height());
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let e = expected.get_pixel_checked(x, y);
+        let a = actual.get_pixel_checked(x, y);
+        match (e, a) {
+            (Some(e), Some(a)) if channel_difference(e, a) <= channel_tolerance => fade(a),
+            _ => HIGHLIGHT_COLOR,
+        }
+    })
+}
+
+/// Place the images next to each other, separated by a small gap.
+pub(crate) fn side_by_side(images: &[&RgbaImage]) -> RgbaImage {
+    let gaps = images.len().saturating_sub(1) as u32 * SEPARATOR_WIDTH;
+    let width = images.iter().map(|i| i.width()).sum::<u32>() + gaps;
+    let height = images.iter().map(|i| i.height()).max().unwrap_or_default();
+
+    let mut result = RgbaImage::from_pixel(width, height, SEPARATOR_COLOR);
+    let mut x = 0;
+    for image in images {
+        imageops::replace(&mut result, *image, x as i64, 0);
+        x += image.width() + SEPARATOR_WIDTH;
+    }
+    result
+}
+
+/// Blend the pixel with white so it only gives a hint of the content.
+fn fade(pixel: &Rgba<u8>) -> Rgba<u8> {
+    let [r, g, b, _] = pixel.0;
+    let fade_channel = |c: u8| 255 - (255 - c) / 4;
+    Rgba([fade_channel(r), fade_channel(g), fade_channel(b), 255])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_changed_pixel() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 2, Rgba([0, 0, 10, 255]));
+
+        let diff = highlight_differences(&expected, &actual, 0);
+        assert_eq!(&HIGHLIGHT_COLOR, diff.get_pixel(1, 2));
+        assert_ne!(&HIGHLIGHT_COLOR, diff.get_pixel(0, 0));
+
+        let composite = side_by_side(&[&expected, &diff, &actual]);
+        assert_eq!(3 * 4 + 2 * SEPARATOR_WIDTH, composite.width());
+        assert_eq!(4, composite.height());
+    }
+
+    #[test]
+    fn pixels_within_tolerance_are_not_highlighted() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 2, Rgba([0, 0, 10, 255]));
+        actual.put_pixel(2, 2, Rgba([0, 0, 11, 255]));
+
+        let diff = highlight_differences(&expected, &actual, 10);
+        assert_ne!(&HIGHLIGHT_COLOR, diff.get_pixel(1, 2));
+        assert_eq!(&HIGHLIGHT_COLOR, diff.get_pixel(2, 2));
+    }
+
+    #[test]
+    fn diff_file_names() {
+        let (diff, composite) = diff_file_paths(Path::new("actual/test_case_a.png"));
+        assert_eq!(Path::new("actual/test_case_a.diff.png"), diff);
+        assert_eq!(Path::new("actual/test_case_a.compare.png"), composite);
+    }
+}
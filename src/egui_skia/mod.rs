@@ -1,25 +1,12 @@
 pub(crate) mod painter;
 
-use egui::Context;
+use std::time::Duration;
+
+use egui::{Context, ViewportId};
 use skia_safe::Canvas;
 
 use crate::egui_skia::painter::Painter;
 
-pub struct RasterizeOptions {
-    pub pixels_per_point: f32,
-    /// The number of frames to render before a screenshot is taken.
-    /// Default is 2, so egui will be able to display windows
-    pub frames_before_screenshot: usize,
-}
-
-impl Default for RasterizeOptions {
-    fn default() -> Self {
-        Self {
-            pixels_per_point: 1.0,
-            frames_before_screenshot: 2,
-        }
-    }
-}
 /// Convenience wrapper for using [`egui`] from a [`skia`] app.
 pub struct EguiSkia {
     pub egui_ctx: Context,
@@ -28,6 +15,7 @@ pub struct EguiSkia {
     shapes: Vec<egui::epaint::ClippedShape>,
     textures_delta: egui::TexturesDelta,
     pixels_per_point: f32,
+    repaint_delay: Duration,
 }
 
 impl EguiSkia {
@@ -39,6 +27,7 @@ impl EguiSkia {
             shapes: Default::default(),
             textures_delta: Default::default(),
             pixels_per_point,
+            repaint_delay: Duration::ZERO,
         }
     }
 
@@ -54,17 +43,28 @@ impl EguiSkia {
             platform_output,
             textures_delta,
             shapes,
-            pixels_per_point: _,
+            pixels_per_point,
             // TODO: How to handle multiple outputs
-            viewport_output: _,
+            viewport_output,
         } = self.egui_ctx.run(input, run_ui);
 
         self.shapes = shapes;
         self.textures_delta.append(textures_delta);
+        self.pixels_per_point = pixels_per_point;
+        self.repaint_delay = viewport_output
+            .get(&ViewportId::ROOT)
+            .map_or(Duration::MAX, |output| output.repaint_delay);
 
         platform_output
     }
 
+    /// Returns the duration after which egui requested to be repainted in the
+    /// last call to [`Self::run`]. [`Duration::MAX`] means that no repaint
+    /// was requested.
+    pub fn repaint_delay(&self) -> Duration {
+        self.repaint_delay
+    }
+
     /// Paint the results of the last call to [`Self::run`].
     pub fn paint(&mut self, canvas: &Canvas) {
         let shapes = std::mem::take(&mut self.shapes);
@@ -72,7 +72,7 @@ impl EguiSkia {
         let clipped_primitives = self.egui_ctx.tessellate(shapes, self.pixels_per_point);
         self.painter.paint_and_update_textures(
             canvas,
-            self.egui_ctx.pixels_per_point(),
+            self.pixels_per_point,
             clipped_primitives,
             textures_delta,
         );
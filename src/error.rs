@@ -0,0 +1,233 @@
+//! Errors and failed outcomes of a screenshot comparison.
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use crate::compare::{DiffStats, Mismatch};
+use crate::metadata::SettingChange;
+use crate::Query;
+
+/// Describes why a screenshot comparison did not succeed.
+#[derive(Debug)]
+pub enum ScreenshotError {
+    /// There is no snapshot file to compare the actual screenshot with.
+    MissingSnapshot {
+        expected_file: PathBuf,
+        actual_file: PathBuf,
+    },
+    /// The snapshot and the actual screenshot have different dimensions.
+    SizeMismatch {
+        expected_file: PathBuf,
+        actual_file: PathBuf,
+        expected: (u32, u32),
+        actual: (u32, u32),
+        /// Settings (e.g. fonts or scale factor) the snapshot was rendered
+        /// with that differ from the actual ones, which are the likely cause.
+        changes: Vec<SettingChange>,
+    },
+    /// The snapshot and the actual screenshot differ more than allowed by the
+    /// configured [`crate::Comparison`].
+    PixelDifference {
+        expected_file: PathBuf,
+        actual_file: PathBuf,
+        stats: DiffStats,
+        /// Settings (e.g. fonts or scale factor) the snapshot was rendered
+        /// with that differ from the actual ones, which are the likely cause.
+        changes: Vec<SettingChange>,
+    },
+    /// Reading or writing a file failed.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// Decoding or encoding an image file failed.
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// The rendered surface could not be encoded as PNG.
+    Encode,
+    /// The region of the screenshot to compare is empty or outside of the
+    /// rendered surface.
+    EmptyRegion,
+    /// No surface with the given output size in egui points can be created,
+    /// e.g. because it is empty.
+    InvalidSize { output_size: (i32, i32) },
+    /// The user interface did not become stable within the given number of
+    /// frames.
+    Unstable { max_frames: usize },
+    /// No widget with a known position matches the query in the last
+    /// rendered frame.
+    NodeNotFound { query: Query },
+    /// A snapshot file name derived from the test function has already been
+    /// used by another assertion.
+    DuplicateSnapshot { expected_file: PathBuf },
+    /// An environment variable that configures the comparison has an invalid
+    /// value.
+    InvalidEnvVar { name: String, message: String },
+}
+
+impl ScreenshotError {
+    pub(crate) fn io(path: &Path, source: std::io::Error) -> Self {
+        ScreenshotError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub(crate) fn image(path: &Path, source: image::ImageError) -> Self {
+        ScreenshotError::Image {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Create the error for a failed comparison. If the snapshot was rendered
+    /// with different settings, these are reported as the likely cause.
+    pub(crate) fn from_mismatch(
+        mismatch: Mismatch,
+        expected_file: PathBuf,
+        actual_file: PathBuf,
+        changes: Vec<SettingChange>,
+    ) -> Self {
+        match mismatch {
+            Mismatch::Size { expected, actual } => ScreenshotError::SizeMismatch {
+                expected_file,
+                actual_file,
+                expected,
+                actual,
+                changes,
+            },
+            Mismatch::Pixels(stats) => ScreenshotError::PixelDifference {
+                expected_file,
+                actual_file,
+                stats,
+                changes,
+            },
+        }
+    }
+
+    /// Whether the snapshot exists but differs from the actual screenshot.
+    pub fn is_mismatch(&self) -> bool {
+        matches!(
+            self,
+            ScreenshotError::SizeMismatch { .. } | ScreenshotError::PixelDifference { .. }
+        )
+    }
+
+    /// Statistics about the differing pixels, if the snapshot and the actual
+    /// screenshot have the same size but differ.
+    pub fn diff_stats(&self) -> Option<&DiffStats> {
+        match self {
+            ScreenshotError::PixelDifference { stats, .. } => Some(stats),
+            _ => None,
+        }
+    }
+
+    /// The settings the snapshot was rendered with that differ from the
+    /// settings of the actual screenshot, if the screenshots differ.
+    pub fn setting_changes(&self) -> &[SettingChange] {
+        match self {
+            ScreenshotError::SizeMismatch { changes, .. }
+            | ScreenshotError::PixelDifference { changes, .. } => changes,
+            _ => &[],
+        }
+    }
+}
+
+/// Append the changed settings to an error message, if there are any.
+fn write_changes(f: &mut std::fmt::Formatter<'_>, changes: &[SettingChange]) -> std::fmt::Result {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let changes: Vec<_> = changes.iter().map(|c| c.to_string()).collect();
+    write!(
+        f,
+        " (the snapshot was created with different settings: {})",
+        changes.join(", ")
+    )
+}
+
+impl Display for ScreenshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScreenshotError::MissingSnapshot { expected_file, .. } => {
+                write!(f, "Snapshot file {:#?} does not exist.", expected_file)
+            }
+            ScreenshotError::SizeMismatch {
+                expected_file,
+                actual_file,
+                expected,
+                actual,
+                changes,
+            } => {
+                write!(
+                    f,
+                    "{} != {}: {}",
+                    actual_file.to_string_lossy(),
+                    expected_file.to_string_lossy(),
+                    Mismatch::Size {
+                        expected: *expected,
+                        actual: *actual
+                    }
+                )?;
+                write_changes(f, changes)
+            }
+            ScreenshotError::PixelDifference {
+                expected_file,
+                actual_file,
+                stats,
+                changes,
+            } => {
+                write!(
+                    f,
+                    "{} != {}: {}",
+                    actual_file.to_string_lossy(),
+                    expected_file.to_string_lossy(),
+                    stats
+                )?;
+                write_changes(f, changes)
+            }
+            ScreenshotError::Io { path, source } => {
+                write!(f, "Could not access {:#?}: {}", path, source)
+            }
+            ScreenshotError::Image { path, source } => {
+                write!(f, "Could not read or write image {:#?}: {}", path, source)
+            }
+            ScreenshotError::Encode => write!(f, "Could not encode screenshot as PNG"),
+            ScreenshotError::EmptyRegion => {
+                write!(f, "The region of the screenshot to compare is empty")
+            }
+            ScreenshotError::InvalidSize { output_size } => write!(
+                f,
+                "Can not create a screenshot with a size of {}x{} points",
+                output_size.0, output_size.1
+            ),
+            ScreenshotError::Unstable { max_frames } => write!(
+                f,
+                "User interface did not become stable after {} frames",
+                max_frames
+            ),
+            ScreenshotError::NodeNotFound { query } => {
+                write!(f, "No widget matching {:?} with a known position found", query)
+            }
+            ScreenshotError::DuplicateSnapshot { expected_file } => write!(
+                f,
+                "Snapshot file {:#?} is used by several assertions, add a suffix to tell them apart",
+                expected_file
+            ),
+            ScreenshotError::InvalidEnvVar { name, message } => {
+                write!(f, "Invalid value of {}: {}", name, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScreenshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScreenshotError::Io { source, .. } => Some(source),
+            ScreenshotError::Image { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Pin the fonts used to render the screenshots, so snapshots do not depend on
+//! the fonts available on the machine that runs the tests.
+use egui::{FontData, FontDefinitions, FontFamily};
+
+use crate::TestBackend;
+
+/// The fonts used to render the screenshots.
+#[derive(Clone, Debug)]
+pub enum FontSetup {
+    /// Only use the fonts that are bundled with egui.
+    Bundled,
+    /// Use the given font definitions.
+    Custom(FontDefinitions),
+}
+
+impl FontSetup {
+    /// Use a single font, given as the bytes of a TTF or OTF file, for both
+    /// proportional and monospace text.
+    pub fn from_font_bytes(name: impl Into<String>, font: Vec<u8>) -> Self {
+        let name = name.into();
+        let mut definitions = FontDefinitions::empty();
+        definitions
+            .font_data
+            .insert(name.clone(), FontData::from_owned(font));
+        for family in [FontFamily::Proportional, FontFamily::Monospace] {
+            definitions.families.insert(family, vec![name.clone()]);
+        }
+        FontSetup::Custom(definitions)
+    }
+
+    /// The font definitions that are applied to the egui context.
+    pub fn font_definitions(&self) -> FontDefinitions {
+        match self {
+            FontSetup::Bundled => FontDefinitions::default(),
+            FontSetup::Custom(definitions) => definitions.clone(),
+        }
+    }
+
+    /// A short hash of the font data and font families, which is stored in
+    /// the snapshot files to detect if a snapshot was created with different
+    /// fonts.
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.font_definitions())
+    }
+}
+
+impl TestBackend {
+    /// Render all screenshots with the given fonts, replacing any fonts the
+    /// application has configured.
+    ///
+    /// Without this setting, the screenshots use whatever fonts the egui
+    /// context has been configured with, which might differ between machines
+    /// if e.g. system fonts are loaded. When the fonts are pinned, a
+    /// fingerprint of the fonts is stored in each snapshot file, so a failed
+    /// comparison caused by changed fonts is reported as such.
+    pub fn with_fonts(mut self, fonts: FontSetup) -> Self {
+        self.backend.egui_ctx.set_fonts(fonts.font_definitions());
+        self.font_fingerprint = Some(fonts.fingerprint());
+        self
+    }
+}
+
+/// Calculate a FNV-1a hash, which unlike the hasher of the standard library is
+/// stable between Rust versions.
+fn fingerprint(definitions: &FontDefinitions) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut update = |bytes: &[u8]| {
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    for (name, data) in &definitions.font_data {
+        update(name.as_bytes());
+        update(&data.font);
+        update(&data.index.to_le_bytes());
+        update(format!("{:?}", data.tweak).as_bytes());
+    }
+    for (family, names) in &definitions.families {
+        update(format!("{:?}", family).as_bytes());
+        for name in names {
+            update(name.as_bytes());
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_depends_on_fonts() {
+        assert_eq!(
+            FontSetup::Bundled.fingerprint(),
+            FontSetup::Bundled.fingerprint()
+        );
+
+        let mut scaled = FontDefinitions::default();
+        for data in scaled.font_data.values_mut() {
+            data.tweak.scale = 2.0;
+        }
+        assert_ne!(
+            FontSetup::Bundled.fingerprint(),
+            FontSetup::Custom(scaled).fingerprint()
+        );
+        assert_ne!(
+            FontSetup::Bundled.fingerprint(),
+            FontSetup::from_font_bytes("font", vec![1, 2, 3]).fingerprint()
+        );
+    }
+}
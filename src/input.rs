@@ -0,0 +1,155 @@
+//! Simulated pointer and keyboard input.
+//!
+//! The input is not sent to egui immediately, but queued and handled when the
+//! next screenshot is taken. Each queued step is handled in a separate frame,
+//! so egui can e.g. detect that the pointer hovers a widget before the button
+//! is pressed.
+use egui::{Event, Key, Modifiers, PointerButton, Pos2};
+
+use crate::TestBackend;
+
+/// Number of intermediate pointer positions used when simulating a drag.
+const DRAG_STEPS: usize = 5;
+
+/// Input that is handled in a single frame.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct InputStep {
+    pub(crate) events: Vec<Event>,
+    /// The modifiers that are held down while the events are handled.
+    pub(crate) modifiers: Modifiers,
+}
+
+impl TestBackend {
+    /// Queue a movement of the pointer to the given position.
+    pub fn pointer_move(&mut self, pos: impl Into<Pos2>) {
+        self.queue_input(vec![Event::PointerMoved(pos.into())]);
+    }
+
+    /// Queue pressing the given pointer `button` at the position `pos`.
+    pub fn pointer_press(&mut self, pos: impl Into<Pos2>, button: PointerButton) {
+        let event = self.pointer_button_event(pos.into(), button, true);
+        self.queue_input(vec![event]);
+    }
+
+    /// Queue releasing the given pointer `button` at the position `pos`.
+    pub fn pointer_release(&mut self, pos: impl Into<Pos2>, button: PointerButton) {
+        let event = self.pointer_button_event(pos.into(), button, false);
+        self.queue_input(vec![event]);
+    }
+
+    /// Queue a click with the primary pointer button at the position `pos`.
+    pub fn click(&mut self, pos: impl Into<Pos2>) {
+        self.pointer_click(pos, PointerButton::Primary);
+    }
+
+    /// Queue a click with the given pointer `button` at the position `pos`.
+    ///
+    /// The pointer is moved to the position first, then the button is pressed
+    /// and released.
+    pub fn pointer_click(&mut self, pos: impl Into<Pos2>, button: PointerButton) {
+        let pos = pos.into();
+        self.pointer_move(pos);
+        self.pointer_press(pos, button);
+        self.pointer_release(pos, button);
+    }
+
+    /// Queue a double click with the primary pointer button at the position
+    /// `pos`.
+    pub fn double_click(&mut self, pos: impl Into<Pos2>) {
+        let pos = pos.into();
+        self.click(pos);
+        self.pointer_press(pos, PointerButton::Primary);
+        self.pointer_release(pos, PointerButton::Primary);
+    }
+
+    /// Queue dragging with the primary pointer button from position `from` to
+    /// position `to`.
+    ///
+    /// The pointer is moved in several steps between the two positions. egui
+    /// only recognizes the movement as a drag if it is longer than the click
+    /// threshold of `egui::InputOptions::max_click_dist`, 6 points by
+    /// default. A shorter drag is handled as a click.
+    pub fn drag(&mut self, from: impl Into<Pos2>, to: impl Into<Pos2>) {
+        let from = from.into();
+        let to = to.into();
+        self.pointer_move(from);
+        self.pointer_press(from, PointerButton::Primary);
+        for step in 1..=DRAG_STEPS {
+            let t = step as f32 / DRAG_STEPS as f32;
+            self.pointer_move(from.lerp(to, t));
+        }
+        self.pointer_release(to, PointerButton::Primary);
+    }
+
+    /// Queue typing the given text, e.g. into a focused
+    /// [`egui::TextEdit`].
+    pub fn type_text(&mut self, text: impl Into<String>) {
+        self.queue_input(vec![Event::Text(text.into())]);
+    }
+
+    /// Queue pressing and releasing the given `key` while the currently held
+    /// modifiers are active.
+    pub fn key_press(&mut self, key: Key) {
+        self.key_down(key);
+        self.key_up(key);
+    }
+
+    /// Queue pressing and releasing the given `key` while the `modifiers`
+    /// are held down additionally to the currently held modifiers, e.g.
+    /// `backend.key_chord(Modifiers::COMMAND, Key::A)` to select all text.
+    pub fn key_chord(&mut self, modifiers: Modifiers, key: Key) {
+        let previous_modifiers = self.modifiers;
+        self.hold_modifiers(modifiers);
+        self.key_press(key);
+        self.modifiers = previous_modifiers;
+    }
+
+    /// Queue pressing the given `key` without releasing it.
+    pub fn key_down(&mut self, key: Key) {
+        let event = self.key_event(key, true);
+        self.queue_input(vec![event]);
+    }
+
+    /// Queue releasing the given `key`.
+    pub fn key_up(&mut self, key: Key) {
+        let event = self.key_event(key, false);
+        self.queue_input(vec![event]);
+    }
+
+    /// Hold down the given `modifiers` for all following input and frames
+    /// until they are released with [`Self::release_modifiers`].
+    pub fn hold_modifiers(&mut self, modifiers: Modifiers) {
+        self.modifiers = self.modifiers | modifiers;
+    }
+
+    /// Release all currently held modifiers.
+    pub fn release_modifiers(&mut self) {
+        self.modifiers = Modifiers::NONE;
+    }
+
+    fn key_event(&self, key: Key, pressed: bool) -> Event {
+        Event::Key {
+            key,
+            physical_key: None,
+            pressed,
+            repeat: false,
+            modifiers: self.modifiers,
+        }
+    }
+
+    fn pointer_button_event(&self, pos: Pos2, button: PointerButton, pressed: bool) -> Event {
+        Event::PointerButton {
+            pos,
+            button,
+            pressed,
+            modifiers: self.modifiers,
+        }
+    }
+
+    pub(crate) fn queue_input(&mut self, events: Vec<Event>) {
+        self.pending_input.push_back(InputStep {
+            events,
+            modifiers: self.modifiers,
+        });
+    }
+}
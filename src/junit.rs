@@ -0,0 +1,91 @@
+//! Convert the collected results into a JUnit XML file, which can be read by
+//! most CI systems.
+use std::path::Path;
+
+use crate::report::escape;
+use crate::{read_report_entries, ReportEntry, ScreenshotError};
+
+/// Name of the test suite that contains all screenshot comparisons.
+const TEST_SUITE_NAME: &str = "egui-screenshot-testing";
+
+/// Read the results from a results file or report directory and write them
+/// as JUnit XML file. Each screenshot comparison is a test case named after
+/// the snapshot file, grouped by the name of the test.
+pub fn write_junit_report(
+    results: impl AsRef<Path>,
+    junit_file: impl AsRef<Path>,
+) -> Result<(), ScreenshotError> {
+    let entries = read_report_entries(results)?;
+    let junit_file = junit_file.as_ref();
+    std::fs::write(junit_file, junit_xml(&entries)).map_err(|e| ScreenshotError::io(junit_file, e))
+}
+
+fn junit_xml(entries: &[ReportEntry]) -> String {
+    let failures = entries.iter().filter(|e| !e.passed).count();
+    let time: f64 = entries.iter().map(|e| e.duration).sum();
+    let summary = format!(
+        "tests=\"{}\" failures=\"{}\" time=\"{:.3}\"",
+        entries.len(),
+        failures,
+        time
+    );
+
+    let mut result = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    result.push_str(&format!("<testsuites {}>\n", summary));
+    result.push_str(&format!(
+        "  <testsuite name=\"{}\" {}>\n",
+        TEST_SUITE_NAME, summary
+    ));
+    for entry in entries {
+        result.push_str(&format!(
+            "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+            escape(&entry.test),
+            escape(&entry.snapshot.to_string_lossy()),
+            entry.duration
+        ));
+        if entry.passed {
+            result.push_str("/>\n");
+            continue;
+        }
+        let message = entry.message.as_deref().unwrap_or("Screenshot differs");
+        let mut details = format!("comparison: {:?}", entry.comparison);
+        if let Some(stats) = &entry.stats {
+            details.push_str(&format!("\n{}", stats));
+        }
+        result.push_str(&format!(
+            ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+            escape(message),
+            escape(&details)
+        ));
+    }
+    result.push_str("  </testsuite>\n</testsuites>\n");
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::Comparison;
+
+    #[test]
+    fn failed_comparison_is_failure() {
+        let entry = |snapshot: &str, passed: bool| ReportEntry {
+            test: "tests::main".to_string(),
+            snapshot: PathBuf::from(snapshot),
+            comparison: Comparison::Exact,
+            duration: 0.5,
+            passed,
+            message: (!passed).then(|| "a.png & b.png differ".to_string()),
+            stats: None,
+            expected_image: None,
+            actual_image: None,
+            diff_image: None,
+        };
+        let xml = junit_xml(&[entry("a.png", true), entry("b.png", false)]);
+        assert!(xml.contains("<testsuites tests=\"2\" failures=\"1\" time=\"1.000\">"));
+        assert!(xml.contains("<testcase classname=\"tests::main\" name=\"a.png\" time=\"0.500\"/>"));
+        assert!(xml.contains("<failure message=\"a.png &amp; b.png differ\">"));
+    }
+}
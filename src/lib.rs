@@ -1,19 +1,97 @@
 //! Helper functions to test [egui](https://github.com/emilk/egui/) applications
 //! using screenshots and comparing them to a saved version.
 //!
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+mod builder;
+mod clock;
+mod compare;
+mod config;
+mod diff;
 mod egui_skia;
+mod error;
+mod fonts;
+mod input;
+mod junit;
+mod mask;
+mod matrix;
+mod metadata;
+mod naming;
+mod orphans;
+mod query;
+mod report;
+mod review;
+mod scenario;
+mod sizing;
+mod update;
+mod widget;
 
 use crate::egui_skia::EguiSkia;
-use egui::Pos2;
-use skia_safe::{surfaces, Surface};
-use visual_hash::HasherConfig;
+use crate::input::InputStep;
+use egui::{accesskit, Modifiers, Pos2, Rect, Vec2};
+use image::DynamicImage;
+use skia_safe::{surfaces, IRect, Surface};
+
+pub use builder::TestBackendBuilder;
+pub use compare::{Comparison, DiffStats, Mismatch, PixelLimit};
+pub use error::ScreenshotError;
+pub use fonts::FontSetup;
+pub use junit::write_junit_report;
+pub use mask::Mask;
+pub use matrix::{Variant, VariantFailure};
+pub use metadata::SettingChange;
+#[doc(hidden)]
+pub use naming::derive_snapshot_name;
+pub use orphans::{find_orphaned_snapshots, read_references, reset_references};
+pub use query::{Node, Query};
+pub use report::{read_report_entries, write_html_report, ReportEntry};
+pub use review::{find_pending_snapshots, PendingSnapshot};
+pub use scenario::{Scenario, StepFailure};
+pub use update::UpdatePolicy;
 
 pub struct TestBackend {
     backend: EguiSkia,
     expected_dir: PathBuf,
     actual_dir: PathBuf,
+    /// Simulated input, each entry is handled in its own frame.
+    pending_input: VecDeque<InputStep>,
+    /// Keyboard modifiers that are currently held down.
+    modifiers: Modifiers,
+    /// The accessibility tree of the last rendered frame.
+    accesskit_tree: Option<accesskit::TreeUpdate>,
+    comparison: Comparison,
+    pixels_per_point: f32,
+    /// Current time of the virtual clock in seconds.
+    time: f64,
+    /// Time in seconds each rendered frame advances the virtual clock.
+    frame_dt: f64,
+    /// Regions that are ignored when comparing screenshots.
+    masks: Vec<Mask>,
+    /// Cached fingerprint of the pinned fonts.
+    font_fingerprint: Option<String>,
+    /// Number of frames rendered since the last screenshot was taken, which
+    /// is stored in the snapshot metadata.
+    frames_since_screenshot: usize,
+    update_policy: UpdatePolicy,
+    /// File the referenced snapshot files are appended to.
+    references_file: Option<PathBuf>,
+    /// Snapshot files that have been compared by this backend.
+    touched_snapshots: Vec<PathBuf>,
+    /// Directory the results of all comparisons are written to.
+    report_dir: Option<PathBuf>,
+    /// File the results of all comparisons are appended to.
+    results_file: Option<PathBuf>,
+    /// When the last screenshot was compared, used to measure the duration
+    /// of each comparison.
+    last_screenshot: Instant,
+    /// Output size in egui points used by [`Self::assert_screenshot`].
+    default_size: (i32, i32),
+    /// Number of frames rendered by [`Self::assert_screenshot`].
+    default_frames: usize,
+    /// Color the surface is filled with before painting.
+    background: Option<egui::Color32>,
 }
 
 /// A backend based on [egui_skia](https://github.com/lucasmerlin/egui_skia)
@@ -27,91 +105,381 @@ impl TestBackend {
     ///   this directory.
     /// * `init_app_with_context` - A closure that will be executed once to init
     ///   the application.
+    ///
+    /// All other settings, e.g. the comparison, the pinned fonts and the scale
+    /// factor, are silently taken from the configuration file
+    /// `egui-screenshot.toml` if it exists, see [`Self::builder`]. Use
+    /// [`TestBackendBuilder::default`] to create a backend that ignores the
+    /// configuration file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration file can not be read or is invalid, e.g.
+    /// if it contains unknown settings or the configured font file does not
+    /// exist.
     pub fn new(
         expected_dir: impl Into<PathBuf>,
         actual_dir: impl Into<PathBuf>,
         init_app_with_context: impl FnOnce(&egui::Context),
     ) -> Self {
-        let backend = EguiSkia::default();
-        init_app_with_context(&backend.egui_ctx);
-        TestBackend {
-            backend,
-            expected_dir: expected_dir.into(),
-            actual_dir: actual_dir.into(),
+        TestBackend::builder()
+            .expected_dir(expected_dir)
+            .actual_dir(actual_dir)
+            .build(init_app_with_context)
+    }
+
+    /// Set the scale factor used to render the screenshots, e.g. `2.0` to
+    /// emulate a HiDPI display. The default is `1.0`.
+    ///
+    /// The output size of the screenshots is given in egui points, so the
+    /// resulting image has a size of `output_size * pixels_per_point` pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels_per_point` is not a positive number.
+    pub fn with_pixels_per_point(mut self, pixels_per_point: f32) -> Self {
+        assert!(
+            pixels_per_point.is_finite() && pixels_per_point > 0.0,
+            "The scale factor must be a positive number"
+        );
+        self.pixels_per_point = pixels_per_point;
+        self
+    }
+
+    /// Set the strategy used to compare the actual screenshot with the
+    /// expected snapshot. Per default, a perceptual hash of both images must
+    /// be equal.
+    pub fn with_comparison(mut self, comparison: Comparison) -> Self {
+        self.comparison = comparison;
+        self
+    }
+
+    /// Fill the screenshots with the given color before painting the user
+    /// interface, which is visible where egui does not paint anything, e.g.
+    /// around windows. Per default, these areas are transparent.
+    pub fn with_background(mut self, color: egui::Color32) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    /// Compare the surface with the snapshot file and add the result to the
+    /// report. If `crop` is given, only this region (in egui points) of the
+    /// surface is compared.
+    fn check_eq_screenshot(
+        &mut self,
+        expected_file_name: &str,
+        surface: &mut Surface,
+        crop: Option<Rect>,
+    ) -> Result<(), ScreenshotError> {
+        let result = self.compare_with_snapshot(expected_file_name, surface, crop);
+        let duration = self.last_screenshot.elapsed();
+        if let Err(e) = self.report_result(expected_file_name, &result, duration) {
+            eprintln!("Warning: could not add the result to the report: {}", e);
         }
+        self.last_screenshot = Instant::now();
+        result
     }
 
-    fn assert_eq_screenshot(
-        &self,
+    fn compare_with_snapshot(
+        &mut self,
         expected_file_name: &str,
         surface: &mut Surface,
-        replace_if_not_equal: bool,
-    ) {
+        crop: Option<Rect>,
+    ) -> Result<(), ScreenshotError> {
+        let update_policy = self.effective_update_policy()?;
         let output_file = self.expected_dir.join(expected_file_name);
+        self.record_reference(&output_file)?;
 
         // Write out the screenshot to a file that is removed if test ist successful
         let actual_file = self.actual_dir.join(expected_file_name);
 
-        std::fs::create_dir_all(actual_file.parent().unwrap()).unwrap();
+        let actual_dir = actual_file.parent().unwrap_or(&self.actual_dir);
+        std::fs::create_dir_all(actual_dir).map_err(|e| ScreenshotError::io(actual_dir, e))?;
 
-        let actual_image_skia = surface.image_snapshot();
+        let actual_image_skia = match crop {
+            Some(crop) => surface
+                .image_snapshot_with_bounds(self.pixel_bounds(crop))
+                .ok_or(ScreenshotError::EmptyRegion)?,
+            None => surface.image_snapshot(),
+        };
         let skia_data = actual_image_skia
             .encode(None, skia_safe::EncodedImageFormat::PNG, 100)
-            .unwrap();
-        std::fs::write(&actual_file, skia_data.as_bytes()).unwrap();
+            .ok_or(ScreenshotError::Encode)?;
+        let actual_metadata = self.snapshot_metadata((
+            actual_image_skia.width() as u32,
+            actual_image_skia.height() as u32,
+        ));
+        self.frames_since_screenshot = 0;
+        let png_data = metadata::with_text_chunks(skia_data.as_bytes(), &actual_metadata)?;
+        std::fs::write(&actual_file, &png_data)
+            .map_err(|e| ScreenshotError::io(&actual_file, e))?;
 
-        if replace_if_not_equal {
-            // Write current snapshot to to expected path
-            let data = actual_image_skia
-                .encode(None, skia_safe::EncodedImageFormat::PNG, 100)
-                .unwrap();
-            std::fs::create_dir_all(output_file.parent().unwrap()).unwrap();
-            std::fs::write(&output_file, data.as_bytes()).unwrap();
+        if update_policy == UpdatePolicy::All
+            || (update_policy.creates_missing() && !output_file.is_file())
+        {
+            self.write_snapshot(&output_file, &png_data)?;
         }
 
         // Read in expected image from file
-        assert!(
-            output_file.is_file(),
-            "Snapshot file {:#?} does not exist.",
-            output_file
+        if !output_file.is_file() {
+            if update_policy == UpdatePolicy::Pending {
+                self.write_pending_snapshot(&output_file, skia_data.as_bytes(), &actual_metadata)?;
+            }
+            return Err(ScreenshotError::MissingSnapshot {
+                expected_file: output_file,
+                actual_file,
+            });
+        }
+        let mut expected_image = read_image(&output_file)?;
+        let changes =
+            metadata::changed_settings(&metadata::read_metadata(&output_file)?, &actual_metadata);
+        let mut actual_image = read_image(&actual_file)?;
+
+        // The cropped image starts at the first pixel of the crop that is
+        // part of the surface
+        let origin = crop.map_or(Vec2::ZERO, |crop| {
+            let bounds = self.pixel_bounds(crop);
+            Vec2::new(bounds.left().max(0) as f32, bounds.top().max(0) as f32)
+        });
+        let masks = self.masked_pixel_rects(origin);
+        if !masks.is_empty() {
+            let mut expected_rgba = expected_image.to_rgba8();
+            let mut actual_rgba = actual_image.to_rgba8();
+            mask::apply_masks(&mut expected_rgba, &masks);
+            mask::apply_masks(&mut actual_rgba, &masks);
+            expected_image = DynamicImage::ImageRgba8(expected_rgba);
+            actual_image = DynamicImage::ImageRgba8(actual_rgba);
+        }
+
+        if let Err(mismatch) = self.comparison.compare(&expected_image, &actual_image) {
+            if update_policy != UpdatePolicy::Mismatching {
+                diff::write_diff_images(
+                    &expected_image,
+                    &actual_image,
+                    &masks,
+                    self.comparison.channel_tolerance(),
+                    &actual_file,
+                )
+                .map_err(|e| ScreenshotError::image(&actual_file, e))?;
+                if update_policy == UpdatePolicy::Pending {
+                    self.write_pending_snapshot(
+                        &output_file,
+                        skia_data.as_bytes(),
+                        &actual_metadata,
+                    )?;
+                }
+                return Err(ScreenshotError::from_mismatch(
+                    mismatch,
+                    output_file,
+                    actual_file,
+                    changes,
+                ));
+            }
+            self.write_snapshot(&output_file, &png_data)?;
+        } else if !changes.is_empty() {
+            let changes: Vec<_> = changes.iter().map(|c| c.to_string()).collect();
+            eprintln!(
+                "Warning: snapshot {:#?} was created with different settings: {}",
+                output_file,
+                changes.join(", ")
+            );
+        }
+
+        // Remove the created file, any diff images and pending snapshot of a
+        // previous run
+        diff::remove_diff_images(&actual_file).map_err(|e| ScreenshotError::io(&actual_file, e))?;
+        review::remove_pending_snapshot(&output_file)
+            .map_err(|e| ScreenshotError::io(&output_file, e))?;
+        std::fs::remove_file(&actual_file).map_err(|e| ScreenshotError::io(&actual_file, e))?;
+        Ok(())
+    }
+
+    /// Write the actual screenshot to the snapshot file.
+    fn write_snapshot(&self, output_file: &Path, png_data: &[u8]) -> Result<(), ScreenshotError> {
+        let output_dir = output_file.parent().unwrap_or(&self.expected_dir);
+        std::fs::create_dir_all(output_dir).map_err(|e| ScreenshotError::io(output_dir, e))?;
+        std::fs::write(output_file, png_data).map_err(|e| ScreenshotError::io(output_file, e))
+    }
+
+    /// Render a given number of frames without taking a screenshot.
+    ///
+    /// * `output_size` - The dimensions of the rendered view in egui points.
+    /// * `n` - Number of times the frame should be rendered.
+    /// * `ui` - Closure that creates the user interface.
+    ///
+    /// Any input queued with e.g. [`Self::click`] or [`Self::type_text`] is
+    /// handled first, one frame per queued step, before the `n` frames are
+    /// rendered. This can be used to let egui lay out the user interface before
+    /// querying widgets with [`Self::find_node`].
+    pub fn run_frames(
+        &mut self,
+        output_size: (i32, i32),
+        n: usize,
+        mut ui: impl FnMut(&egui::Context),
+    ) {
+        let input = self.raw_input(output_size);
+        self.run_pending_input(&input, &mut ui);
+
+        for _ in 0..n {
+            self.run_frame(input.clone(), &mut ui);
+        }
+    }
+
+    /// Render frames until egui does not request a repaint and the rendered
+    /// output does not change anymore. Returns the surface with the last
+    /// rendered frame.
+    fn render_until_stable(
+        &mut self,
+        output_size: (i32, i32),
+        max_frames: usize,
+        mut ui: impl FnMut(&egui::Context),
+    ) -> Result<Surface, ScreenshotError> {
+        self.surface_size(output_size)?;
+        let input = self.raw_input(output_size);
+        self.run_pending_input(&input, &mut ui);
+
+        let mut previous_pixels = None;
+        for _ in 0..max_frames {
+            self.run_frame(input.clone(), &mut ui);
+
+            let mut surface = self.create_surface(output_size)?;
+            self.paint(&mut surface);
+            let pixels = surface
+                .peek_pixels()
+                .and_then(|pixmap| pixmap.bytes().map(|bytes| bytes.to_vec()));
+
+            let repaint_requested = self.backend.repaint_delay() != Duration::MAX;
+            if !repaint_requested && pixels.is_some() && pixels == previous_pixels {
+                return Ok(surface);
+            }
+            previous_pixels = pixels;
+        }
+        Err(ScreenshotError::Unstable { max_frames })
+    }
+
+    fn raw_input(&self, output_size: (i32, i32)) -> egui::RawInput {
+        let screen_rect = Rect::from_min_size(
+            Pos2::default(),
+            Vec2::new(output_size.0 as f32, output_size.1 as f32),
         );
-        let expected_image = image::io::Reader::open(&output_file)
-            .unwrap()
-            .with_guessed_format()
-            .unwrap()
-            .decode()
-            .unwrap();
-        let actual_image = image::io::Reader::open(&actual_file)
-            .unwrap()
-            .with_guessed_format()
-            .unwrap()
-            .decode()
-            .unwrap();
+        let viewport = egui::ViewportInfo {
+            native_pixels_per_point: Some(self.pixels_per_point),
+            inner_rect: Some(screen_rect),
+            focused: Some(true),
+            ..Default::default()
+        };
+        egui::RawInput {
+            screen_rect: Some(screen_rect),
+            viewports: std::iter::once((egui::ViewportId::ROOT, viewport)).collect(),
+            modifiers: self.modifiers,
+            ..Default::default()
+        }
+    }
 
-        // Compare images using a visual hash
-        let hasher = HasherConfig::default().to_hasher();
-        let expected_hash = hasher.hash_image(&expected_image);
-        let actual_hash = hasher.hash_image(&actual_image);
+    /// Handle all queued input, one frame per queued step.
+    fn run_pending_input(&mut self, input: &egui::RawInput, mut ui: impl FnMut(&egui::Context)) {
+        while let Some(step) = self.pending_input.pop_front() {
+            let input_with_events = egui::RawInput {
+                events: step.events,
+                modifiers: step.modifiers,
+                ..input.clone()
+            };
+            self.run_frame(input_with_events, &mut ui);
+        }
+    }
 
-        let dist = actual_hash.dist(&expected_hash);
-        assert!(
-            dist == 0,
-            "{} != {}",
-            actual_file.to_string_lossy(),
-            output_file.to_string_lossy(),
+    /// The size of the surface in pixels for the given size in points, which
+    /// must not be empty.
+    fn surface_size(&self, output_size: (i32, i32)) -> Result<(i32, i32), ScreenshotError> {
+        let size = (
+            (output_size.0 as f32 * self.pixels_per_point).round() as i32,
+            (output_size.1 as f32 * self.pixels_per_point).round() as i32,
         );
+        if size.0 <= 0 || size.1 <= 0 {
+            return Err(ScreenshotError::InvalidSize { output_size });
+        }
+        Ok(size)
+    }
+
+    /// Create an empty surface for a screenshot with the given size in
+    /// points.
+    fn create_surface(&self, output_size: (i32, i32)) -> Result<Surface, ScreenshotError> {
+        surfaces::raster_n32_premul(self.surface_size(output_size)?)
+            .ok_or(ScreenshotError::InvalidSize { output_size })
+    }
+
+    /// The smallest region of the surface in pixels that contains the given
+    /// rectangle in points.
+    fn pixel_bounds(&self, rect: Rect) -> IRect {
+        let min = (rect.min.to_vec2() * self.pixels_per_point).floor();
+        let max = (rect.max.to_vec2() * self.pixels_per_point).ceil();
+        IRect::from_ltrb(min.x as i32, min.y as i32, max.x as i32, max.y as i32)
+    }
+
+    /// Paint the last rendered frame on the surface, on top of the
+    /// background color if one is set.
+    fn paint(&mut self, surface: &mut Surface) {
+        if let Some(background) = self.background {
+            let [r, g, b, a] = background.to_srgba_unmultiplied();
+            surface
+                .canvas()
+                .clear(skia_safe::Color::from_argb(a, r, g, b));
+        }
+        self.backend.paint(surface.canvas());
+    }
+
+    fn run_frame(&mut self, mut input: egui::RawInput, ui: impl FnMut(&egui::Context)) {
+        self.tick(&mut input);
+        self.frames_since_screenshot += 1;
+        let platform_output = self.backend.run(input, ui);
+        if let Some(accesskit_update) = platform_output.accesskit_update {
+            self.accesskit_tree = Some(accesskit_update);
+        }
+    }
+
+    /// Assert that the rendered view is the same as the snapshot, using the
+    /// default output size and number of frames configured with
+    /// [`Self::builder`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the actual and expected screenshots are not the same or if
+    /// the given snapshot file to compare against does not exist.
+    pub fn assert_screenshot(&mut self, expected_file_name: &str, ui: impl FnMut(&egui::Context)) {
+        if let Err(e) = self.check_screenshot(expected_file_name, ui) {
+            panic!("{}", e);
+        }
+    }
 
-        // Remove the created file
-        std::fs::remove_file(actual_file).unwrap();
+    /// Check that the rendered view is the same as the snapshot, using the
+    /// default output size and number of frames.
+    ///
+    /// This works like [`Self::assert_screenshot`], but returns an error
+    /// instead of panicking.
+    pub fn check_screenshot(
+        &mut self,
+        expected_file_name: &str,
+        ui: impl FnMut(&egui::Context),
+    ) -> Result<(), ScreenshotError> {
+        self.check_screenshot_after_n_frames(
+            expected_file_name,
+            self.default_size,
+            self.default_frames,
+            ui,
+        )
     }
 
     /// Assert that the rendered view is the same after a given number of rendered frames.
     ///
     /// * `expected_file_name` - The file name of the snapshot.
-    /// * `output_size` - The dimensions of the screenshot.
+    /// * `output_size` - The dimensions of the screenshot in egui points.
     /// * `n` - Number of times the frame should be rendered before the screenshot is compared. If you use animations or other effects, this helps to render the final version.
     /// * `ui` - Closure that creates the user interface.
     ///
+    /// Any input queued with e.g. [`Self::click`] or [`Self::type_text`] is
+    /// handled first, one frame per queued step, before the `n` frames are
+    /// rendered.
+    ///
     /// # Panics
     ///
     /// Similar to the inbuilt `assert_` macros, this will panic if the actual and expected screenshots are not the same.
@@ -121,38 +489,139 @@ impl TestBackend {
         expected_file_name: &str,
         output_size: (i32, i32),
         n: usize,
-        mut ui: impl FnMut(&egui::Context),
+        ui: impl FnMut(&egui::Context),
     ) {
-        let mut surface =
-            surfaces::raster_n32_premul(output_size).expect("Failed to create surface");
-        let input = egui::RawInput {
-            screen_rect: Some(
-                [
-                    Pos2::default(),
-                    Pos2::new(surface.width() as f32, surface.height() as f32),
-                ]
-                .into(),
-            ),
-            ..Default::default()
-        };
+        if let Err(e) = self.check_screenshot_after_n_frames(expected_file_name, output_size, n, ui)
+        {
+            panic!("{}", e);
+        }
+    }
 
-        for _ in 0..n {
-            self.backend.run(input.clone(), &mut ui);
+    /// Check that the rendered view is the same after a given number of
+    /// rendered frames.
+    ///
+    /// This works like [`Self::assert_screenshot_after_n_frames`], but returns
+    /// an error describing the difference instead of panicking.
+    pub fn check_screenshot_after_n_frames(
+        &mut self,
+        expected_file_name: &str,
+        output_size: (i32, i32),
+        n: usize,
+        ui: impl FnMut(&egui::Context),
+    ) -> Result<(), ScreenshotError> {
+        let mut surface = self.create_surface(output_size)?;
+        self.run_frames(output_size, n, ui);
+
+        self.paint(&mut surface);
+        self.check_eq_screenshot(expected_file_name, &mut surface, None)
+    }
+
+    /// Assert that the rendered view is the same as the snapshot after the
+    /// user interface became stable.
+    ///
+    /// * `expected_file_name` - The file name of the snapshot.
+    /// * `output_size` - The dimensions of the screenshot in egui points.
+    /// * `max_frames` - Maximum number of frames to render before giving up.
+    /// * `ui` - Closure that creates the user interface.
+    ///
+    /// Frames are rendered until egui does not request a repaint anymore and
+    /// the rendered output is the same as in the previous frame. Use this
+    /// instead of guessing the number of frames needed for windows or
+    /// animations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the user interface does not become stable within
+    /// `max_frames`, if the actual and expected screenshots are not the same
+    /// or if the given snapshot file to compare against does not exist.
+    pub fn assert_screenshot_when_stable(
+        &mut self,
+        expected_file_name: &str,
+        output_size: (i32, i32),
+        max_frames: usize,
+        ui: impl FnMut(&egui::Context),
+    ) {
+        if let Err(e) =
+            self.check_screenshot_when_stable(expected_file_name, output_size, max_frames, ui)
+        {
+            panic!("{}", e);
         }
+    }
 
-        self.backend.paint(surface.canvas());
-        let replace_if_not_equal = std::env::var("EGUI_SCREENSHOT_REPLACE").is_ok();
-        self.assert_eq_screenshot(expected_file_name, &mut surface, replace_if_not_equal);
+    /// Check that the rendered view is the same as the snapshot after the
+    /// user interface became stable.
+    ///
+    /// This works like [`Self::assert_screenshot_when_stable`], but returns
+    /// an error instead of panicking.
+    pub fn check_screenshot_when_stable(
+        &mut self,
+        expected_file_name: &str,
+        output_size: (i32, i32),
+        max_frames: usize,
+        ui: impl FnMut(&egui::Context),
+    ) -> Result<(), ScreenshotError> {
+        let mut surface = self.render_until_stable(output_size, max_frames, ui)?;
+        self.check_eq_screenshot(expected_file_name, &mut surface, None)
     }
 }
 
+fn create_egui_backend() -> EguiSkia {
+    let backend = EguiSkia::default();
+    // The accessibility tree is used to find widgets by their label
+    backend.egui_ctx.enable_accesskit();
+    backend
+}
+
+fn read_image(path: &Path) -> Result<DynamicImage, ScreenshotError> {
+    image::io::Reader::open(path)
+        .map_err(|e| ScreenshotError::io(path, e))?
+        .with_guessed_format()
+        .map_err(|e| ScreenshotError::io(path, e))?
+        .decode()
+        .map_err(|e| ScreenshotError::image(path, e))
+}
+
 #[cfg(test)]
 mod tests {
 
-    use tempfile::tempdir;
+    use tempfile::{tempdir, TempDir};
 
     use super::*;
 
+    /// Run `f` with the snapshot and actual directories inside `out_dir`,
+    /// while `EGUI_SCREENSHOT_REPLACE` is set so all snapshots are written.
+    /// Returns the snapshot directory.
+    fn write_snapshots(out_dir: &TempDir, f: impl FnOnce(&Path, &Path)) -> PathBuf {
+        let expected = out_dir.path().join("expected");
+        let actual = out_dir.path().join("actual");
+        temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
+            f(&expected, &actual)
+        });
+        expected
+    }
+
+    /// A user interface that only shows a heading.
+    fn hello_world(ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Hello World");
+        });
+    }
+
+    /// A user interface that only shows the given heading.
+    fn heading(text: &'static str) -> impl Fn(&egui::Context) {
+        move |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.heading(text);
+            });
+        }
+    }
+
+    /// Run `f` without the environment variables that override the update
+    /// policy, so only the policy of the backend is used.
+    fn without_update_env(f: impl FnOnce()) {
+        temp_env::with_vars_unset(["EGUI_SCREENSHOT_REPLACE", "EGUI_SCREENSHOT_UPDATE"], f);
+    }
+
     #[test]
     fn readme_example() {
         let mut backend = TestBackend::new("src/tests/expected", "src/tests/actual", |_ctx| {
@@ -181,11 +650,7 @@ mod tests {
                 "should_not_exist.png",
                 (150, 100),
                 5,
-                move |ctx| {
-                    egui::CentralPanel::default().show(ctx, |ui| {
-                        ui.heading("Hello World");
-                    });
-                },
+                hello_world,
             );
         });
     }
@@ -194,24 +659,918 @@ mod tests {
     fn replace_env_variable() {
         let out_dir = tempdir().unwrap();
 
-        let expected = out_dir.path().join("expected");
+        let expected = write_snapshots(&out_dir, |expected, actual| {
+            let mut backend = TestBackend::new(expected, actual, |_ctx| {});
+            backend.assert_screenshot_after_n_frames(
+                "will_be_created_by_env.png",
+                (150, 100),
+                5,
+                hello_world,
+            );
+        });
+
         let actual = out_dir.path().join("actual");
+        assert_eq!(true, expected.join("will_be_created_by_env.png").is_file());
+        assert_eq!(false, actual.join("will_be_created_by_env.png").exists());
+    }
+
+    #[test]
+    fn click_button() {
+        let out_dir = tempdir().unwrap();
+        let clicked = std::rc::Rc::new(std::cell::Cell::new(0));
 
         temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
-            let mut backend = TestBackend::new(&expected, &actual, |_ctx| {});
-            backend.assert_screenshot_after_n_frames(
-                "will_be_created_by_env.png",
+            let mut backend = TestBackend::new(
+                out_dir.path().join("expected"),
+                out_dir.path().join("actual"),
+                |_ctx| {},
+            );
+            let clicked = clicked.clone();
+            let ui = move |ctx: &egui::Context| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let button = ui.put(
+                        egui::Rect::from_min_size(Pos2::new(10.0, 10.0), [80.0, 20.0].into()),
+                        egui::Button::new("Click me"),
+                    );
+                    if button.clicked() {
+                        clicked.set(clicked.get() + 1);
+                    }
+                });
+            };
+            backend.click((50.0, 20.0));
+            backend.assert_screenshot_after_n_frames("clicked.png", (150, 100), 1, ui);
+        });
+
+        assert_eq!(1, clicked.get());
+    }
+
+    #[test]
+    fn press_and_release_button() {
+        let out_dir = tempdir().unwrap();
+        let held = std::rc::Rc::new(std::cell::Cell::new(false));
+        let clicked = std::rc::Rc::new(std::cell::Cell::new(0));
+
+        let mut backend = TestBackend::new(
+            out_dir.path().join("expected"),
+            out_dir.path().join("actual"),
+            |_ctx| {},
+        );
+        let mut ui = {
+            let held = held.clone();
+            let clicked = clicked.clone();
+            move |ctx: &egui::Context| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let button = ui.put(
+                        egui::Rect::from_min_size(Pos2::new(10.0, 10.0), [80.0, 20.0].into()),
+                        egui::Button::new("Click me"),
+                    );
+                    held.set(button.is_pointer_button_down_on());
+                    if button.clicked() {
+                        clicked.set(clicked.get() + 1);
+                    }
+                });
+            }
+        };
+        backend.pointer_move((50.0, 20.0));
+        backend.pointer_press((50.0, 20.0), egui::PointerButton::Primary);
+        backend.run_frames((150, 100), 1, &mut ui);
+        assert!(held.get());
+        assert_eq!(0, clicked.get());
+
+        backend.pointer_release((50.0, 20.0), egui::PointerButton::Primary);
+        backend.run_frames((150, 100), 1, &mut ui);
+        assert!(!held.get());
+        assert_eq!(1, clicked.get());
+    }
+
+    #[test]
+    fn double_click_button() {
+        let out_dir = tempdir().unwrap();
+        let double_clicked = std::rc::Rc::new(std::cell::Cell::new(0));
+
+        let mut backend = TestBackend::new(
+            out_dir.path().join("expected"),
+            out_dir.path().join("actual"),
+            |_ctx| {},
+        );
+        let ui = {
+            let double_clicked = double_clicked.clone();
+            move |ctx: &egui::Context| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let button = ui.put(
+                        egui::Rect::from_min_size(Pos2::new(10.0, 10.0), [80.0, 20.0].into()),
+                        egui::Button::new("Click me"),
+                    );
+                    if button.double_clicked() {
+                        double_clicked.set(double_clicked.get() + 1);
+                    }
+                });
+            }
+        };
+        backend.double_click((50.0, 20.0));
+        backend.run_frames((150, 100), 1, ui);
+
+        assert_eq!(1, double_clicked.get());
+    }
+
+    #[test]
+    fn drag_across_area() {
+        let out_dir = tempdir().unwrap();
+        let dragged = std::rc::Rc::new(std::cell::Cell::new(false));
+        let drag_stopped = std::rc::Rc::new(std::cell::Cell::new(0));
+        let clicked = std::rc::Rc::new(std::cell::Cell::new(0));
+
+        let mut backend = TestBackend::new(
+            out_dir.path().join("expected"),
+            out_dir.path().join("actual"),
+            |_ctx| {},
+        );
+        let ui = {
+            let dragged = dragged.clone();
+            let drag_stopped = drag_stopped.clone();
+            let clicked = clicked.clone();
+            move |ctx: &egui::Context| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let response = ui.interact(
+                        egui::Rect::from_min_size(Pos2::new(10.0, 10.0), [130.0, 80.0].into()),
+                        egui::Id::new("area"),
+                        egui::Sense::click_and_drag(),
+                    );
+                    if response.dragged() {
+                        dragged.set(true);
+                    }
+                    if response.drag_stopped() {
+                        drag_stopped.set(drag_stopped.get() + 1);
+                    }
+                    if response.clicked() {
+                        clicked.set(clicked.get() + 1);
+                    }
+                });
+            }
+        };
+        backend.drag((20.0, 20.0), (120.0, 80.0));
+        backend.run_frames((150, 100), 1, ui);
+
+        assert!(dragged.get());
+        assert_eq!(1, drag_stopped.get());
+        assert_eq!(0, clicked.get());
+    }
+
+    #[test]
+    fn type_into_text_edit() {
+        let out_dir = tempdir().unwrap();
+        let text = std::rc::Rc::new(std::cell::RefCell::new(String::new()));
+
+        temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
+            let mut backend = TestBackend::new(
+                out_dir.path().join("expected"),
+                out_dir.path().join("actual"),
+                |_ctx| {},
+            );
+            let text = text.clone();
+            let ui = move |ctx: &egui::Context| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.put(
+                        egui::Rect::from_min_size(Pos2::new(10.0, 10.0), [120.0, 20.0].into()),
+                        egui::TextEdit::singleline(&mut *text.borrow_mut()),
+                    );
+                });
+            };
+            backend.click((50.0, 20.0));
+            backend.type_text("Hello World");
+            backend.key_chord(Modifiers::COMMAND, egui::Key::A);
+            backend.type_text("Replaced");
+            backend.assert_screenshot_after_n_frames("typed.png", (150, 100), 1, ui);
+        });
+
+        assert_eq!("Replaced", text.borrow().as_str());
+    }
+
+    #[test]
+    fn click_node_by_label() {
+        let out_dir = tempdir().unwrap();
+        let clicked = std::rc::Rc::new(std::cell::Cell::new(0));
+
+        temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
+            let mut backend = TestBackend::new(
+                out_dir.path().join("expected"),
+                out_dir.path().join("actual"),
+                |_ctx| {},
+            );
+            let clicked = clicked.clone();
+            let mut ui = move |ctx: &egui::Context| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.label("Some text");
+                    if ui.button("Click me").clicked() {
+                        clicked.set(clicked.get() + 1);
+                    }
+                });
+            };
+            backend.run_frames((150, 100), 1, &mut ui);
+
+            let node = backend
+                .find_node(&Query::label("Click me").with_role(accesskit::Role::Button))
+                .unwrap();
+            assert_eq!(Some("Click me"), node.label.as_deref());
+            assert!(node.rect.is_some());
+            assert!(backend.find_node(&Query::label("Does not exist")).is_none());
+
+            backend.click_node(&Query::label("Click me"));
+            backend.assert_screenshot_after_n_frames("clicked_node.png", (150, 100), 1, ui);
+        });
+
+        assert_eq!(1, clicked.get());
+    }
+
+    #[test]
+    fn check_returns_missing_snapshot() {
+        let out_dir = tempdir().unwrap();
+
+        temp_env::with_var_unset("EGUI_SCREENSHOT_REPLACE", || {
+            let mut backend = TestBackend::new(
+                "src/tests/expected",
+                out_dir.path().join("actual"),
+                |_ctx| {},
+            );
+            let result = backend.check_screenshot_after_n_frames(
+                "should_not_exist.png",
                 (150, 100),
                 5,
-                move |ctx| {
+                hello_world,
+            );
+            assert!(matches!(
+                result,
+                Err(ScreenshotError::MissingSnapshot { .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn scaled_screenshot_size() {
+        let out_dir = tempdir().unwrap();
+        let expected = write_snapshots(&out_dir, |expected, actual| {
+            let mut backend =
+                TestBackend::new(expected, actual, |_ctx| {}).with_pixels_per_point(1.5);
+            backend.assert_screenshot_after_n_frames("scaled.png", (150, 100), 5, hello_world);
+        });
+
+        let image = read_image(&expected.join("scaled.png")).unwrap();
+        assert_eq!((225, 150), (image.width(), image.height()));
+    }
+
+    #[test]
+    fn never_stable_ui_fails() {
+        let out_dir = tempdir().unwrap();
+        let mut backend = TestBackend::new(
+            out_dir.path().join("expected"),
+            out_dir.path().join("actual"),
+            |_ctx| {},
+        );
+        let mut counter = 0;
+        let result = backend.check_screenshot_when_stable("unstable.png", (150, 100), 10, |ctx| {
+            counter += 1;
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.heading(format!("Frame {counter}"));
+            });
+        });
+        assert!(matches!(
+            result,
+            Err(ScreenshotError::Unstable { max_frames: 10 })
+        ));
+    }
+
+    #[test]
+    fn stable_ui() {
+        let out_dir = tempdir().unwrap();
+
+        temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
+            let mut backend = TestBackend::new(
+                out_dir.path().join("expected"),
+                out_dir.path().join("actual"),
+                |_ctx| {},
+            );
+            backend.assert_screenshot_when_stable("stable.png", (150, 100), 10, |ctx| {
+                egui::Window::new("Window").show(ctx, |ui| {
+                    ui.label("Windows need several frames to be positioned");
+                });
+            });
+        });
+    }
+
+    #[test]
+    fn virtual_clock() {
+        let out_dir = tempdir().unwrap();
+        let mut backend = TestBackend::new(
+            out_dir.path().join("expected"),
+            out_dir.path().join("actual"),
+            |_ctx| {},
+        )
+        .with_frame_dt(Duration::from_millis(100));
+
+        let mut frame_times = Vec::new();
+        let mut ui = |ctx: &egui::Context| {
+            frame_times.push(ctx.input(|i| i.time));
+        };
+        backend.run_frames((150, 100), 2, &mut ui);
+        backend.advance_time(Duration::from_secs(1));
+        backend.run_for((150, 100), Duration::from_millis(200), &mut ui);
+
+        assert_eq!(4, frame_times.len());
+        for (expected, actual) in [0.0, 0.1, 1.2, 1.3].iter().zip(frame_times) {
+            assert!((expected - actual).abs() < 1e-6);
+        }
+        assert!((backend.time().as_secs_f64() - 1.4).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_frame_dt_is_rejected() {
+        let out_dir = tempdir().unwrap();
+        TestBackend::builder()
+            .expected_dir(out_dir.path().join("expected"))
+            .actual_dir(out_dir.path().join("actual"))
+            .frame_dt(Duration::ZERO)
+            .build(|_ctx| {});
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_pixels_per_point_is_rejected() {
+        let out_dir = tempdir().unwrap();
+        TestBackend::builder()
+            .expected_dir(out_dir.path().join("expected"))
+            .actual_dir(out_dir.path().join("actual"))
+            .pixels_per_point(0.0)
+            .build(|_ctx| {});
+    }
+
+    #[test]
+    fn empty_size_is_an_error() {
+        let out_dir = tempdir().unwrap();
+        let mut backend = TestBackend::new(
+            out_dir.path().join("expected"),
+            out_dir.path().join("actual"),
+            |_ctx| {},
+        );
+        for size in [(0, 100), (150, -1)] {
+            let result = backend.check_screenshot_after_n_frames("empty.png", size, 1, hello_world);
+            assert!(matches!(
+                result,
+                Err(ScreenshotError::InvalidSize { output_size }) if output_size == size
+            ));
+        }
+        let result = backend
+            .check_widget_screenshot("empty.png", (0, 0), 1, 0.0, |ui| ui.label("Hello World"));
+        assert!(matches!(result, Err(ScreenshotError::InvalidSize { .. })));
+    }
+
+    #[test]
+    fn scenario_with_several_snapshots() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+        let mut counter = 0;
+
+        temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
+            let mut backend = TestBackend::new(&expected, out_dir.path().join("actual"), |_ctx| {});
+            backend
+                .scenario((150, 100), |ctx| {
                     egui::CentralPanel::default().show(ctx, |ui| {
-                        ui.heading("Hello World");
+                        if ui.button("Click me").clicked() {
+                            counter += 1;
+                        }
+                        ui.label(format!("Clicked {} times", counter));
                     });
-                },
+                })
+                .frames(2)
+                .snapshot("scenario_step_1.png")
+                .click_node(Query::label("Click me"))
+                .frames(2)
+                .snapshot("scenario_step_2.png")
+                .assert();
+        });
+
+        assert_eq!(1, counter);
+        assert!(expected.join("scenario_step_1.png").is_file());
+        assert!(expected.join("scenario_step_2.png").is_file());
+    }
+
+    #[test]
+    fn scenario_reports_missing_node() {
+        let out_dir = tempdir().unwrap();
+        let mut backend = TestBackend::new(
+            out_dir.path().join("expected"),
+            out_dir.path().join("actual"),
+            |_ctx| {},
+        );
+        let failures = backend
+            .scenario((150, 100), |_ctx| {})
+            .frames(1)
+            .click_node(Query::label("Does not exist"))
+            .check()
+            .unwrap_err();
+        assert_eq!(1, failures.len());
+        assert_eq!(2, failures[0].step);
+        assert!(matches!(
+            failures[0].error,
+            ScreenshotError::NodeNotFound { .. }
+        ));
+    }
+
+    #[test]
+    fn masked_region_is_ignored() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+        let actual = out_dir.path().join("actual");
+
+        let ui = |text: &'static str| {
+            move |ctx: &egui::Context| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.heading("Hello World");
+                    ui.label(egui::RichText::new(text).monospace());
+                });
+            }
+        };
+
+        temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
+            let mut backend = TestBackend::new(&expected, &actual, |_ctx| {});
+            backend.assert_screenshot_after_n_frames("masked.png", (150, 100), 2, ui("12:00"));
+        });
+
+        temp_env::with_var_unset("EGUI_SCREENSHOT_REPLACE", || {
+            let mut backend =
+                TestBackend::new(&expected, &actual, |_ctx| {}).with_comparison(Comparison::Exact);
+            assert!(backend
+                .check_screenshot_after_n_frames("masked.png", (150, 100), 2, ui("13:37"))
+                .is_err());
+
+            backend.add_mask(Mask::Rect(egui::Rect::from_min_size(
+                Pos2::new(0.0, 30.0),
+                Vec2::new(150.0, 30.0),
+            )));
+            backend.assert_screenshot_after_n_frames("masked.png", (150, 100), 2, ui("13:37"));
+        });
+    }
+
+    #[test]
+    fn widget_screenshot_is_cropped() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+
+        temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
+            let mut backend = TestBackend::new(&expected, out_dir.path().join("actual"), |_ctx| {});
+            backend.assert_widget_screenshot("widget.png", (150, 100), 2, 2.0, |ui| {
+                ui.add_space(20.0);
+                ui.add_sized([40.0, 20.0], egui::Button::new("Ok"))
+            });
+        });
+
+        let image = read_image(&expected.join("widget.png")).unwrap();
+        assert_eq!((44, 24), (image.width(), image.height()));
+    }
+
+    #[test]
+    fn auto_size_fits_content() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+
+        temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
+            let mut backend = TestBackend::new(&expected, out_dir.path().join("actual"), |_ctx| {});
+            backend.assert_screenshot_auto_size("auto_size.png", (800, 600), 3, |ctx| {
+                egui::Area::new(egui::Id::new("content"))
+                    .fixed_pos(Pos2::ZERO)
+                    .show(ctx, |ui| {
+                        ui.add_sized([60.0, 30.0], egui::Button::new("Ok"));
+                    });
+            });
+        });
+
+        let image = read_image(&expected.join("auto_size.png")).unwrap();
+        assert_eq!((60, 30), (image.width(), image.height()));
+    }
+
+    #[test]
+    fn theme_variants() {
+        let out_dir = tempdir().unwrap();
+        let expected = write_snapshots(&out_dir, |expected, actual| {
+            let mut backend = TestBackend::new(expected, actual, |_ctx| {});
+            backend.assert_screenshot_variants(
+                "themed.png",
+                &[Variant::dark(), Variant::light()],
+                (150, 100),
+                2,
+                hello_world,
             );
+            // The original style is restored
+            assert!(backend.backend.egui_ctx.style().visuals.dark_mode);
         });
 
-        assert_eq!(true, expected.join("will_be_created_by_env.png").is_file());
-        assert_eq!(false, actual.join("will_be_created_by_env.png").exists());
+        assert!(expected.join("themed_dark.png").is_file());
+        assert!(expected.join("themed_light.png").is_file());
+    }
+
+    #[test]
+    fn variants_do_not_depend_on_their_order() {
+        let out_dir = tempdir().unwrap();
+        let mut compact = egui::Style::default();
+        compact.spacing.item_spacing = Vec2::new(0.0, 40.0);
+        let ui = |ctx: &egui::Context| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.label("First");
+                ui.label("Second");
+            });
+        };
+
+        let expected = write_snapshots(&out_dir, |expected, actual| {
+            let mut backend = TestBackend::new(expected, actual, |_ctx| {});
+            backend.assert_screenshot_variants(
+                "ordered.png",
+                &[Variant::style("compact", compact), Variant::dark()],
+                (150, 100),
+                2,
+                ui,
+            );
+            let mut backend = TestBackend::new(expected, actual, |_ctx| {});
+            backend.assert_screenshot_variants("single.png", &[Variant::dark()], (150, 100), 2, ui);
+        });
+
+        let single = read_image(&expected.join("single_dark.png")).unwrap();
+        let ordered = read_image(&expected.join("ordered_dark.png")).unwrap();
+        assert_eq!(Ok(()), Comparison::Exact.compare(&single, &ordered));
+    }
+
+    #[test]
+    fn style_is_restored_after_panic() {
+        let out_dir = tempdir().unwrap();
+        let mut backend = TestBackend::new(
+            out_dir.path().join("expected"),
+            out_dir.path().join("actual"),
+            |_ctx| {},
+        );
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            backend.assert_screenshot_variants(
+                "panic.png",
+                &[Variant::light()],
+                (150, 100),
+                1,
+                |_ctx| panic!("Creating the user interface failed"),
+            );
+        }));
+        assert!(result.is_err());
+        assert!(backend.backend.egui_ctx.style().visuals.dark_mode);
+    }
+
+    #[test]
+    fn size_variants() {
+        let out_dir = tempdir().unwrap();
+        let expected = write_snapshots(&out_dir, |expected, actual| {
+            let mut backend = TestBackend::new(expected, actual, |_ctx| {});
+            backend.assert_screenshot_sizes("sized.png", &[(150, 100), (300, 200)], 2, hello_world);
+        });
+
+        let image = read_image(&expected.join("sized@150x100.png")).unwrap();
+        assert_eq!((150, 100), (image.width(), image.height()));
+        let image = read_image(&expected.join("sized@300x200.png")).unwrap();
+        assert_eq!((300, 200), (image.width(), image.height()));
+    }
+
+    #[test]
+    fn size_matrix_matches_single_size() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+        let init = |ctx: &egui::Context| ctx.set_visuals(egui::Visuals::light());
+        let ui = |ctx: &egui::Context| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.heading(format!("Time {:.2}", ctx.input(|i| i.time)));
+            });
+        };
+
+        temp_env::with_var("EGUI_SCREENSHOT_REPLACE", Some("1"), || {
+            let mut backend = TestBackend::new(&expected, out_dir.path().join("actual"), init);
+            backend.assert_screenshot_after_n_frames("single.png", (150, 100), 2, ui);
+
+            let mut backend = TestBackend::new(&expected, out_dir.path().join("actual"), init);
+            backend.assert_screenshot_sizes("matrix.png", &[(300, 200), (150, 100)], 2, ui);
+        });
+
+        let single = read_image(&expected.join("single.png")).unwrap();
+        let matrix = read_image(&expected.join("matrix@150x100.png")).unwrap();
+        assert_eq!(Ok(()), Comparison::Exact.compare(&single, &matrix));
+    }
+
+    #[test]
+    fn size_matrix_clears_animations() {
+        let out_dir = tempdir().unwrap();
+        let ui = |ctx: &egui::Context| {
+            let wide = ctx.available_rect().width() > 200.0;
+            let progress = ctx.animate_bool_with_time(egui::Id::new("wide"), wide, 1.0);
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.heading(format!("Progress {:.2}", progress));
+            });
+        };
+
+        let expected = write_snapshots(&out_dir, |expected, actual| {
+            let mut backend = TestBackend::new(expected, actual, |_ctx| {});
+            backend.assert_screenshot_after_n_frames("single.png", (150, 100), 2, ui);
+
+            let mut backend = TestBackend::new(expected, actual, |_ctx| {});
+            backend.assert_screenshot_sizes("animated.png", &[(300, 200), (150, 100)], 2, ui);
+        });
+
+        let single = read_image(&expected.join("single.png")).unwrap();
+        let matrix = read_image(&expected.join("animated@150x100.png")).unwrap();
+        assert_eq!(Ok(()), Comparison::Exact.compare(&single, &matrix));
+    }
+
+    #[test]
+    fn changed_fonts_are_reported() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+        let actual = out_dir.path().join("actual");
+
+        write_snapshots(&out_dir, |expected, actual| {
+            let mut backend =
+                TestBackend::new(expected, actual, |_ctx| {}).with_fonts(FontSetup::Bundled);
+            backend.assert_screenshot_after_n_frames("fonts.png", (150, 100), 2, hello_world);
+        });
+
+        let mut scaled = egui::FontDefinitions::default();
+        for data in scaled.font_data.values_mut() {
+            data.tweak.scale = 2.0;
+        }
+        without_update_env(|| {
+            let mut backend = TestBackend::new(&expected, &actual, |_ctx| {})
+                .with_fonts(FontSetup::Custom(scaled));
+            let result =
+                backend.check_screenshot_after_n_frames("fonts.png", (150, 100), 2, hello_world);
+            match result {
+                Err(ScreenshotError::PixelDifference { changes, .. }) => {
+                    assert_eq!(1, changes.len());
+                    assert_eq!("font-fingerprint", changes[0].name);
+                }
+                _ => panic!("Changed fonts not reported"),
+            }
+        });
+    }
+
+    #[test]
+    fn snapshot_contains_metadata() {
+        let out_dir = tempdir().unwrap();
+        let expected = write_snapshots(&out_dir, |expected, actual| {
+            let mut backend =
+                TestBackend::new(expected, actual, |_ctx| {}).with_pixels_per_point(2.0);
+            backend.assert_screenshot_after_n_frames("metadata.png", (150, 100), 3, hello_world);
+        });
+
+        let metadata = metadata::read_metadata(&expected.join("metadata.png")).unwrap();
+        assert_eq!("2", metadata["pixels-per-point"]);
+        assert_eq!("3", metadata["frames"]);
+        assert_eq!("300x200", metadata["surface-size"]);
+        assert_eq!(env!("CARGO_PKG_VERSION"), metadata["crate-version"]);
+    }
+
+    #[test]
+    fn update_policies() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+        let actual = out_dir.path().join("actual");
+
+        without_update_env(|| {
+            let mut backend = TestBackend::new(&expected, &actual, |_ctx| {})
+                .with_comparison(Comparison::Exact)
+                .with_update_policy(UpdatePolicy::Missing);
+            // Missing snapshots are created, but existing ones are not changed
+            backend.assert_screenshot_after_n_frames("policy.png", (150, 100), 2, heading("A"));
+            let result =
+                backend.check_screenshot_after_n_frames("policy.png", (150, 100), 2, heading("B"));
+            assert!(matches!(
+                result,
+                Err(ScreenshotError::PixelDifference { .. })
+            ));
+
+            // Mismatching snapshots are updated
+            let mut backend = TestBackend::new(&expected, &actual, |_ctx| {})
+                .with_comparison(Comparison::Exact)
+                .with_update_policy(UpdatePolicy::Mismatching);
+            backend.assert_screenshot_after_n_frames("policy.png", (150, 100), 2, heading("B"));
+
+            let mut backend =
+                TestBackend::new(&expected, &actual, |_ctx| {}).with_comparison(Comparison::Exact);
+            backend.assert_screenshot_after_n_frames("policy.png", (150, 100), 2, heading("B"));
+        });
+
+        // The environment variable overrides the configured policy
+        temp_env::with_var("EGUI_SCREENSHOT_UPDATE", Some("never"), || {
+            let mut backend = TestBackend::new(&expected, &actual, |_ctx| {})
+                .with_comparison(Comparison::Exact)
+                .with_update_policy(UpdatePolicy::All);
+            let result =
+                backend.check_screenshot_after_n_frames("other.png", (150, 100), 2, heading("A"));
+            assert!(matches!(
+                result,
+                Err(ScreenshotError::MissingSnapshot { .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn referenced_snapshots_are_recorded() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+        let references_file = out_dir.path().join("references.txt");
+        std::fs::create_dir_all(&expected).unwrap();
+        std::fs::write(expected.join("orphaned.png"), "").unwrap();
+
+        write_snapshots(&out_dir, |expected, actual| {
+            let mut backend = TestBackend::new(expected, actual, |_ctx| {})
+                .with_references_file(&references_file);
+            backend.assert_screenshot_after_n_frames("used.png", (150, 100), 2, hello_world);
+            assert_eq!(&[expected.join("used.png")], backend.touched_snapshots());
+        });
+
+        let references = read_references(&references_file).unwrap();
+        assert_eq!(
+            vec![expected.join("orphaned.png")],
+            find_orphaned_snapshots(&expected, &references).unwrap()
+        );
+    }
+
+    #[test]
+    fn results_are_added_to_report() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+        let report_dir = out_dir.path().join("report");
+
+        without_update_env(|| {
+            let mut backend = TestBackend::new(&expected, out_dir.path().join("actual"), |_ctx| {})
+                .with_comparison(Comparison::Exact)
+                .with_update_policy(UpdatePolicy::Missing)
+                .with_report_dir(&report_dir);
+            backend.assert_screenshot_after_n_frames("report.png", (150, 100), 2, heading("A"));
+            assert!(backend
+                .check_screenshot_after_n_frames("report.png", (150, 100), 2, heading("B"))
+                .is_err());
+        });
+
+        let entries = read_report_entries(&report_dir).unwrap();
+        assert_eq!(2, entries.len());
+        assert!(entries[0].passed);
+        assert!(entries[0].actual_image.is_none());
+        assert!(!entries[1].passed);
+        assert!(entries[1].stats.as_ref().unwrap().differing_pixels > 0);
+        for image in [
+            &entries[1].expected_image,
+            &entries[1].actual_image,
+            &entries[1].diff_image,
+        ] {
+            assert!(report_dir.join(image.as_ref().unwrap()).is_file());
+        }
+        assert!(write_html_report(&report_dir).unwrap().is_file());
+    }
+
+    #[test]
+    fn results_file_and_junit_report() {
+        let out_dir = tempdir().unwrap();
+        let results_file = out_dir.path().join("results.jsonl");
+        let junit_file = out_dir.path().join("junit.xml");
+
+        without_update_env(|| {
+            let mut backend = TestBackend::new(
+                out_dir.path().join("expected"),
+                out_dir.path().join("actual"),
+                |_ctx| {},
+            )
+            .with_comparison(Comparison::Exact)
+            .with_results_file(&results_file);
+            assert!(backend
+                .check_screenshot_after_n_frames("missing.png", (150, 100), 2, |_ctx| {})
+                .is_err());
+        });
+
+        let entries = read_report_entries(&results_file).unwrap();
+        assert_eq!(1, entries.len());
+        assert_eq!(Comparison::Exact, entries[0].comparison);
+        assert!(!entries[0].passed);
+        assert!(entries[0].expected_image.is_none());
+
+        write_junit_report(&results_file, &junit_file).unwrap();
+        let xml = std::fs::read_to_string(junit_file).unwrap();
+        assert!(xml.contains("failures=\"1\""));
+    }
+
+    #[test]
+    fn builder_defaults() {
+        let out_dir = tempdir().unwrap();
+        let expected = write_snapshots(&out_dir, |expected, actual| {
+            let mut backend = TestBackend::builder()
+                .expected_dir(expected)
+                .actual_dir(actual)
+                .default_size((60, 40))
+                .frames(2)
+                .background(egui::Color32::RED)
+                .build(|_ctx| {});
+            backend.assert_screenshot("builder.png", |_ctx| {});
+        });
+
+        let image = read_image(&expected.join("builder.png"))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!((60, 40), image.dimensions());
+        assert_eq!(&image::Rgba([255, 0, 0, 255]), image.get_pixel(30, 20));
+        let metadata = metadata::read_metadata(&expected.join("builder.png")).unwrap();
+        assert_eq!("2", metadata["frames"]);
+        assert_eq!("#ff0000ff", metadata["background"]);
+    }
+
+    #[test]
+    fn new_uses_config_file() {
+        let out_dir = tempdir().unwrap();
+        std::fs::write(
+            out_dir.path().join("egui-screenshot.toml"),
+            "default_size = [60, 40]\nframes = 2\n",
+        )
+        .unwrap();
+
+        let crate_dir = Some(out_dir.path().as_os_str());
+        let expected = temp_env::with_vars(
+            [
+                ("CARGO_MANIFEST_DIR", crate_dir),
+                ("EGUI_SCREENSHOT_CONFIG", None),
+            ],
+            || {
+                write_snapshots(&out_dir, |expected, actual| {
+                    let mut backend = TestBackend::new(expected, actual, |_ctx| {});
+                    backend.assert_screenshot("config.png", hello_world);
+                })
+            },
+        );
+
+        let image = read_image(&expected.join("config.png")).unwrap();
+        assert_eq!((60, 40), (image.width(), image.height()));
+    }
+
+    #[test]
+    fn derived_snapshot_names() {
+        let out_dir = tempdir().unwrap();
+        let expected = write_snapshots(&out_dir, |expected, actual| {
+            let mut backend = TestBackend::builder()
+                .expected_dir(expected)
+                .actual_dir(actual)
+                .default_size((150, 100))
+                .build(|_ctx| {});
+            crate::assert_screenshot!(backend, hello_world);
+            crate::assert_screenshot!(backend, "suffix", hello_world);
+            let result = crate::check_screenshot!(backend, "suffix", hello_world);
+            assert!(matches!(
+                result,
+                Err(ScreenshotError::DuplicateSnapshot { .. })
+            ));
+        });
+
+        assert!(expected
+            .join("egui_screenshot_testing__tests__derived_snapshot_names.png")
+            .is_file());
+        assert!(expected
+            .join("egui_screenshot_testing__tests__derived_snapshot_names-suffix.png")
+            .is_file());
+    }
+
+    #[test]
+    fn pending_snapshots() {
+        let out_dir = tempdir().unwrap();
+        let expected = out_dir.path().join("expected");
+        let actual = out_dir.path().join("actual");
+
+        without_update_env(|| {
+            let mut backend = TestBackend::new(&expected, &actual, |_ctx| {})
+                .with_comparison(Comparison::Exact)
+                .with_update_policy(UpdatePolicy::Pending);
+            let result =
+                backend.check_screenshot_after_n_frames("new.png", (150, 100), 2, heading("A"));
+            assert!(matches!(
+                result,
+                Err(ScreenshotError::MissingSnapshot { .. })
+            ));
+            assert!(!expected.join("new.png").exists());
+
+            let pending = find_pending_snapshots(&expected).unwrap();
+            assert_eq!(1, pending.len());
+            assert!(pending[0].is_new());
+            pending[0].accept().unwrap();
+            assert!(!metadata::read_metadata(&expected.join("new.png"))
+                .unwrap()
+                .contains_key("pending"));
+            backend.assert_screenshot_after_n_frames("new.png", (150, 100), 2, heading("A"));
+
+            assert!(backend
+                .check_screenshot_after_n_frames("new.png", (150, 100), 2, heading("B"))
+                .is_err());
+            let pending = find_pending_snapshots(&expected).unwrap();
+            assert_eq!(1, pending.len());
+            assert!(pending[0].mismatch().unwrap().is_some());
+
+            // A matching screenshot removes the outdated pending snapshot
+            backend.assert_screenshot_after_n_frames("new.png", (150, 100), 2, heading("A"));
+            assert!(find_pending_snapshots(&expected).unwrap().is_empty());
+        });
     }
 }
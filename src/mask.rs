@@ -0,0 +1,193 @@
+//! Regions of the screenshot that are ignored when comparing it with the
+//! snapshot, e.g. because they show a clock or random content.
+use image::math::Rect as PixelRect;
+use image::{Rgba, RgbaImage};
+
+use crate::{Query, TestBackend};
+
+/// Color that masked regions are filled with before comparing the images.
+const MASK_FILL_COLOR: Rgba<u8> = Rgba([255, 0, 255, 255]);
+/// Color that masked regions are tinted with in the diff images.
+const MASK_TINT_COLOR: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+/// A region that is ignored when comparing screenshots.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mask {
+    /// A rectangle given in egui points.
+    Rect(egui::Rect),
+    /// The area of the widget with the given id in the last rendered frame.
+    Widget(egui::Id),
+}
+
+impl TestBackend {
+    /// Ignore the given region in all following screenshot comparisons.
+    pub fn add_mask(&mut self, mask: Mask) {
+        self.masks.push(mask);
+    }
+
+    /// Compare the whole screenshot again.
+    pub fn clear_masks(&mut self) {
+        self.masks.clear();
+    }
+
+    /// Get the masked regions of the last rendered frame in pixels, relative
+    /// to the given `origin` in pixels.
+    ///
+    /// Masks for widgets that are not part of the frame are ignored.
+    pub(crate) fn masked_pixel_rects(&self, origin: egui::Vec2) -> Vec<PixelRect> {
+        self.masks
+            .iter()
+            .filter_map(|mask| match mask {
+                Mask::Rect(rect) => Some(*rect),
+                Mask::Widget(id) => self.find_node(&Query::id(*id)).and_then(|n| n.rect),
+            })
+            .map(|rect| to_pixel_rect(rect, self.pixels_per_point, origin))
+            .collect()
+    }
+}
+
+/// Convert the rectangle in points to the pixels it covers, relative to the
+/// `origin` in pixels.
+fn to_pixel_rect(rect: egui::Rect, pixels_per_point: f32, origin: egui::Vec2) -> PixelRect {
+    // Parts of the rectangle left or above of the image are ignored
+    let min = ((rect.min.to_vec2() * pixels_per_point).floor() - origin).max(egui::Vec2::ZERO);
+    let max = ((rect.max.to_vec2() * pixels_per_point).ceil() - origin).max(min);
+    PixelRect {
+        x: min.x as u32,
+        y: min.y as u32,
+        width: (max.x - min.x) as u32,
+        height: (max.y - min.y) as u32,
+    }
+}
+
+/// Iterate over all pixel coordinates of the rectangle that are inside the
+/// image.
+fn pixels_in<'a>(image: &RgbaImage, rect: &'a PixelRect) -> impl Iterator<Item = (u32, u32)> + 'a {
+    let x_end = rect.x.saturating_add(rect.width).min(image.width());
+    let y_end = rect.y.saturating_add(rect.height).min(image.height());
+    (rect.y..y_end).flat_map(move |y| (rect.x..x_end).map(move |x| (x, y)))
+}
+
+/// Fill all masked regions with the same color, so they are equal in both
+/// images.
+pub(crate) fn apply_masks(image: &mut RgbaImage, masks: &[PixelRect]) {
+    for rect in masks {
+        for (x, y) in pixels_in(image, rect) {
+            image.put_pixel(x, y, MASK_FILL_COLOR);
+        }
+    }
+}
+
+/// Blend the masked regions with a tint color, so reviewers can see which
+/// regions have been ignored.
+pub(crate) fn tint_masks(image: &mut RgbaImage, masks: &[PixelRect]) {
+    for rect in masks {
+        for (x, y) in pixels_in(image, rect) {
+            let pixel = image.get_pixel_mut(x, y);
+            for (channel, tint) in pixel.0.iter_mut().zip(MASK_TINT_COLOR.0).take(3) {
+                *channel = ((*channel as u16 + tint as u16) / 2) as u8;
+            }
+            pixel.0[3] = 255;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masked_regions_are_equal() {
+        let mut expected = RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(2, 3, Rgba([255, 255, 255, 255]));
+
+        let mask = to_pixel_rect(
+            egui::Rect::from_min_max([1.5, 2.0].into(), [3.0, 4.0].into()),
+            1.0,
+            egui::Vec2::ZERO,
+        );
+        assert_eq!(
+            PixelRect {
+                x: 1,
+                y: 2,
+                width: 2,
+                height: 2
+            },
+            mask
+        );
+
+        apply_masks(&mut expected, &[mask]);
+        apply_masks(&mut actual, &[mask]);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn mask_left_of_image_is_clipped() {
+        let mask = to_pixel_rect(
+            egui::Rect::from_min_max([-5.0, -2.0].into(), [3.0, 4.0].into()),
+            1.0,
+            egui::Vec2::ZERO,
+        );
+        assert_eq!(
+            PixelRect {
+                x: 0,
+                y: 0,
+                width: 3,
+                height: 4
+            },
+            mask
+        );
+
+        let mask = to_pixel_rect(
+            egui::Rect::from_min_max([-5.0, -5.0].into(), [-1.0, -1.0].into()),
+            1.0,
+            egui::Vec2::ZERO,
+        );
+        assert_eq!(0, mask.width);
+        assert_eq!(0, mask.height);
+    }
+
+    #[test]
+    fn mask_is_relative_to_pixel_origin() {
+        // A crop starting at 1 point begins at pixel 1 with a scale factor of
+        // 1.5, so the mask at 2 points (pixel 3) starts at pixel 2 of the crop
+        let mask = to_pixel_rect(
+            egui::Rect::from_min_max([2.0, 2.0].into(), [3.0, 3.0].into()),
+            1.5,
+            egui::Vec2::new(1.0, 1.0),
+        );
+        assert_eq!(
+            PixelRect {
+                x: 2,
+                y: 2,
+                width: 2,
+                height: 2
+            },
+            mask
+        );
+    }
+
+    #[test]
+    fn mask_outside_of_image() {
+        let mut image = RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 255]));
+        let mask = PixelRect {
+            x: 8,
+            y: 8,
+            width: 100,
+            height: 100,
+        };
+        apply_masks(&mut image, &[mask]);
+        assert_eq!(&MASK_FILL_COLOR, image.get_pixel(9, 9));
+        assert_eq!(&Rgba([0, 0, 0, 255]), image.get_pixel(7, 7));
+
+        let huge_mask = PixelRect {
+            x: 1,
+            y: 1,
+            width: u32::MAX,
+            height: u32::MAX,
+        };
+        apply_masks(&mut image, &[huge_mask]);
+        assert_eq!(&MASK_FILL_COLOR, image.get_pixel(1, 1));
+    }
+}
@@ -0,0 +1,317 @@
+//! Render the same user interface under several configurations and store one
+//! snapshot per configuration.
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::Arc;
+
+use egui::{accesskit, Visuals};
+
+use crate::input::InputStep;
+use crate::{ScreenshotError, TestBackend};
+
+/// A configuration of the egui context, e.g. a theme, under which a snapshot
+/// is taken.
+pub struct Variant {
+    name: String,
+    apply: Box<dyn Fn(&egui::Context)>,
+}
+
+impl Variant {
+    /// Create a variant with the given name that configures the context with
+    /// the `apply` closure before rendering. The name is appended to the file
+    /// name of the snapshot.
+    pub fn new(name: impl Into<String>, apply: impl Fn(&egui::Context) + 'static) -> Self {
+        Variant {
+            name: name.into(),
+            apply: Box::new(apply),
+        }
+    }
+
+    /// The dark theme of egui.
+    pub fn dark() -> Self {
+        Variant::new("dark", |ctx| ctx.set_visuals(Visuals::dark()))
+    }
+
+    /// The light theme of egui.
+    pub fn light() -> Self {
+        Variant::new("light", |ctx| ctx.set_visuals(Visuals::light()))
+    }
+
+    /// A custom style.
+    pub fn style(name: impl Into<String>, style: egui::Style) -> Self {
+        Variant::new(name, move |ctx| ctx.set_style(style.clone()))
+    }
+
+    /// The name of the variant, which is appended to the snapshot file name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A snapshot of a variant that did not match.
+#[derive(Debug)]
+pub struct VariantFailure {
+    /// The name of the variant.
+    pub variant: String,
+    pub error: ScreenshotError,
+}
+
+impl Display for VariantFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Variant {}: {}", self.variant, self.error)
+    }
+}
+
+/// Insert the `suffix` between the file stem and the extension of the given
+/// file name, e.g. `main.png` with suffix `_dark` becomes `main_dark.png`.
+pub(crate) fn file_name_with_suffix(file_name: &str, suffix: &str) -> String {
+    let path = Path::new(file_name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let new_file_name = match path.extension() {
+        Some(extension) => format!("{}{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}{}", stem, suffix),
+    };
+    path.with_file_name(new_file_name)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Restores the style of the context when dropped, so a panicking variant
+/// does not leave its style behind.
+struct RestoreStyle {
+    ctx: egui::Context,
+    style: Arc<egui::Style>,
+}
+
+impl Drop for RestoreStyle {
+    fn drop(&mut self) {
+        self.ctx.set_style(self.style.clone());
+    }
+}
+
+/// The state of the backend when rendering a matrix of snapshots starts. It
+/// is restored before each configuration is rendered, so the snapshots do
+/// not depend on the order of the configurations.
+struct MatrixStart {
+    style: Arc<egui::Style>,
+    memory: egui::Memory,
+    accesskit_tree: Option<accesskit::TreeUpdate>,
+    pending_input: VecDeque<InputStep>,
+    time: f64,
+    frames_since_screenshot: usize,
+}
+
+/// Panic with a message listing all failures.
+pub(crate) fn panic_on_failures(failures: Vec<VariantFailure>) {
+    let messages: Vec<_> = failures.iter().map(|f| f.to_string()).collect();
+    panic!("Variants failed:\n{}", messages.join("\n"));
+}
+
+impl TestBackend {
+    /// Remember the current state of the backend and take the queued input,
+    /// which is replayed for each configuration of the matrix.
+    fn start_matrix(&mut self) -> MatrixStart {
+        MatrixStart {
+            style: self.backend.egui_ctx.style(),
+            memory: self.backend.egui_ctx.memory(|m| m.clone()),
+            accesskit_tree: self.accesskit_tree.take(),
+            pending_input: std::mem::take(&mut self.pending_input),
+            time: self.time,
+            frames_since_screenshot: self.frames_since_screenshot,
+        }
+    }
+
+    /// Reset the backend to the start of the matrix before rendering the next
+    /// configuration. Running animations are cleared, since their state is
+    /// not part of the egui memory and can not be restored.
+    fn reset_to_matrix_start(&mut self, start: &MatrixStart) {
+        let ctx = &self.backend.egui_ctx;
+        ctx.memory_mut(|m| *m = start.memory.clone());
+        ctx.set_style(start.style.clone());
+        ctx.clear_animations();
+        self.time = start.time;
+        self.frames_since_screenshot = start.frames_since_screenshot;
+        self.pending_input = start.pending_input.clone();
+    }
+
+    /// Restore the state of the start of the matrix after all configurations
+    /// have been rendered. The virtual clock continues at `end_time`.
+    fn finish_matrix(&mut self, start: MatrixStart, end_time: f64) {
+        let ctx = &self.backend.egui_ctx;
+        ctx.memory_mut(|m| *m = start.memory);
+        ctx.set_style(start.style);
+        ctx.clear_animations();
+        self.accesskit_tree = start.accesskit_tree;
+        self.time = end_time;
+    }
+
+    /// Assert that the rendered view is the same as the snapshot for each of
+    /// the given variants, e.g. the dark and light theme.
+    ///
+    /// * `expected_file_name` - The file name of the snapshot. The name of
+    ///   each variant is appended, so `main.png` becomes `main_dark.png` for
+    ///   [`Variant::dark`].
+    /// * `variants` - The configurations to render the user interface with.
+    /// * `output_size` - The dimensions of the screenshot in egui points.
+    /// * `n` - Number of times the frame should be rendered before the
+    ///   screenshot is compared.
+    /// * `ui` - Closure that creates the user interface.
+    ///
+    /// Each variant is applied to the style, egui memory and virtual clock of
+    /// the time this function is called, so the snapshots do not depend on the
+    /// order of the variants. Running animations are cleared and any queued
+    /// input is replayed for each variant. Afterwards, the style and egui
+    /// memory are restored and the clock continues after the latest rendered
+    /// frame. The style is also restored if rendering a variant panics.
+    ///
+    /// # Panics
+    ///
+    /// Panics after all variants have been compared if any of them did not
+    /// match its snapshot, listing all failed variants.
+    pub fn assert_screenshot_variants(
+        &mut self,
+        expected_file_name: &str,
+        variants: &[Variant],
+        output_size: (i32, i32),
+        n: usize,
+        ui: impl FnMut(&egui::Context),
+    ) {
+        if let Err(failures) =
+            self.check_screenshot_variants(expected_file_name, variants, output_size, n, ui)
+        {
+            panic_on_failures(failures);
+        }
+    }
+
+    /// Check that the rendered view is the same as the snapshot for each of
+    /// the given variants.
+    ///
+    /// This works like [`Self::assert_screenshot_variants`], but returns all
+    /// failed variants instead of panicking.
+    pub fn check_screenshot_variants(
+        &mut self,
+        expected_file_name: &str,
+        variants: &[Variant],
+        output_size: (i32, i32),
+        n: usize,
+        mut ui: impl FnMut(&egui::Context),
+    ) -> Result<(), Vec<VariantFailure>> {
+        let _restore_style = RestoreStyle {
+            ctx: self.backend.egui_ctx.clone(),
+            style: self.backend.egui_ctx.style(),
+        };
+        let start = self.start_matrix();
+        let mut end_time = start.time;
+        let mut failures = Vec::new();
+        for variant in variants {
+            self.reset_to_matrix_start(&start);
+            (variant.apply)(&self.backend.egui_ctx);
+            let file_name =
+                file_name_with_suffix(expected_file_name, &format!("_{}", variant.name));
+            let result = self.check_screenshot_after_n_frames(&file_name, output_size, n, &mut ui);
+            end_time = end_time.max(self.time);
+
+            if let Err(error) = result {
+                failures.push(VariantFailure {
+                    variant: variant.name.clone(),
+                    error,
+                });
+            }
+        }
+        self.finish_matrix(start, end_time);
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+}
+
+impl TestBackend {
+    /// Assert that the rendered view is the same as the snapshot for each of
+    /// the given output sizes, e.g. to test responsive layouts.
+    ///
+    /// * `expected_file_name` - The file name of the snapshot. The size is
+    ///   appended, so `main.png` becomes `main@300x200.png`.
+    /// * `sizes` - The dimensions of the screenshots in egui points.
+    /// * `n` - Number of times the frame should be rendered before the
+    ///   screenshot is compared.
+    /// * `ui` - Closure that creates the user interface.
+    ///
+    /// All sizes are rendered with the egui context of this backend, so
+    /// everything set up by the init closure is kept. Each size starts with
+    /// the style, egui memory (e.g. window positions) and virtual clock of the
+    /// time this function is called, so the snapshots do not depend on the
+    /// order of the sizes. Running animations are cleared and any queued input
+    /// is replayed for each size. Afterwards, the egui memory is restored and
+    /// the clock continues after the latest rendered frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics after all sizes have been compared if any of them did not match
+    /// its snapshot, listing all failed sizes.
+    pub fn assert_screenshot_sizes(
+        &mut self,
+        expected_file_name: &str,
+        sizes: &[(i32, i32)],
+        n: usize,
+        ui: impl FnMut(&egui::Context),
+    ) {
+        if let Err(failures) = self.check_screenshot_sizes(expected_file_name, sizes, n, ui) {
+            panic_on_failures(failures);
+        }
+    }
+
+    /// Check that the rendered view is the same as the snapshot for each of
+    /// the given output sizes.
+    ///
+    /// This works like [`Self::assert_screenshot_sizes`], but returns all
+    /// failed sizes instead of panicking.
+    pub fn check_screenshot_sizes(
+        &mut self,
+        expected_file_name: &str,
+        sizes: &[(i32, i32)],
+        n: usize,
+        mut ui: impl FnMut(&egui::Context),
+    ) -> Result<(), Vec<VariantFailure>> {
+        let start = self.start_matrix();
+        let mut end_time = start.time;
+        let mut failures = Vec::new();
+        for size in sizes {
+            let variant = format!("{}x{}", size.0, size.1);
+            let file_name = file_name_with_suffix(expected_file_name, &format!("@{}", variant));
+
+            self.reset_to_matrix_start(&start);
+            let result = self.check_screenshot_after_n_frames(&file_name, *size, n, &mut ui);
+            end_time = end_time.max(self.time);
+
+            if let Err(error) = result {
+                failures.push(VariantFailure { variant, error });
+            }
+        }
+        self.finish_matrix(start, end_time);
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suffix_is_added_before_extension() {
+        assert_eq!("main_dark.png", file_name_with_suffix("main.png", "_dark"));
+        assert_eq!("main_dark", file_name_with_suffix("main", "_dark"));
+        assert_eq!(
+            "sub/main@300x200.png",
+            file_name_with_suffix("sub/main.png", "@300x200")
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Information about how a screenshot was rendered, stored as text chunks in
+//! the PNG files.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::{ScreenshotError, TestBackend};
+
+/// Prefix of the keywords of all text chunks written by this crate.
+const KEY_PREFIX: &str = "egui-screenshot-testing:";
+
+const EGUI_VERSION_KEY: &str = "egui-version";
+const CRATE_VERSION_KEY: &str = "crate-version";
+const PIXELS_PER_POINT_KEY: &str = "pixels-per-point";
+const FRAMES_KEY: &str = "frames";
+const COMPARISON_KEY: &str = "comparison";
+const SURFACE_SIZE_KEY: &str = "surface-size";
+const FONT_FINGERPRINT_KEY: &str = "font-fingerprint";
+const BACKGROUND_KEY: &str = "background";
+/// Marks a pending snapshot, see [`crate::UpdatePolicy::Pending`]. It is
+/// removed when the pending snapshot is accepted.
+pub(crate) const PENDING_KEY: &str = "pending";
+
+/// Settings that are stored for information only, but never compared, since
+/// they change without affecting the rendered screenshot.
+const INFORMATIONAL_KEYS: [&str; 3] = [CRATE_VERSION_KEY, FRAMES_KEY, COMPARISON_KEY];
+
+/// The version of egui this crate is linked against. egui does not export
+/// its version, so it is looked up once in the dependency graph of the crate
+/// under test, which is reported by `cargo metadata`. `None` if the tests are
+/// not run by cargo or the lookup fails.
+fn egui_version() -> Option<&'static str> {
+    static EGUI_VERSION: OnceLock<Option<String>> = OnceLock::new();
+    EGUI_VERSION.get_or_init(read_egui_version).as_deref()
+}
+
+/// Run `cargo metadata` for the crate under test and find the version of
+/// egui in its dependency graph.
+fn read_egui_version() -> Option<String> {
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")?;
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = std::process::Command::new(cargo)
+        .args([
+            "metadata",
+            "--format-version",
+            "1",
+            "--offline",
+            "--manifest-path",
+        ])
+        .arg(Path::new(&manifest_dir).join("Cargo.toml"))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let metadata = serde_json::from_slice(&output.stdout).ok()?;
+    egui_version_in(&metadata)
+}
+
+/// The version of the egui package this crate depends on in the output of
+/// `cargo metadata`. Other versions of egui in the dependency graph, e.g.
+/// of other dependencies, are ignored.
+fn egui_version_in(metadata: &serde_json::Value) -> Option<String> {
+    let packages = metadata["packages"].as_array()?;
+    let this_crate = packages.iter().find(|package| {
+        package["name"] == env!("CARGO_PKG_NAME") && package["version"] == env!("CARGO_PKG_VERSION")
+    })?;
+    let node = metadata["resolve"]["nodes"]
+        .as_array()?
+        .iter()
+        .find(|node| node["id"] == this_crate["id"])?;
+    let egui_id = &node["deps"]
+        .as_array()?
+        .iter()
+        .find(|dep| dep["name"] == "egui")?["pkg"];
+    let egui = packages.iter().find(|package| &package["id"] == egui_id)?;
+    egui["version"].as_str().map(str::to_string)
+}
+
+/// A setting that differs between the snapshot and the actual screenshot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettingChange {
+    /// The name of the setting, e.g. `pixels-per-point`.
+    pub name: String,
+    /// The value stored in the snapshot file.
+    pub expected: String,
+    /// The value used to render the actual screenshot.
+    pub actual: String,
+}
+
+impl Display for SettingChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} != {}", self.name, self.actual, self.expected)
+    }
+}
+
+/// Compare the metadata of two screenshots. Settings that are only stored in
+/// one of them, e.g. because the snapshot was created by an older version of
+/// this crate, and informational settings like the crate version are ignored.
+pub(crate) fn changed_settings(
+    expected: &BTreeMap<String, String>,
+    actual: &BTreeMap<String, String>,
+) -> Vec<SettingChange> {
+    actual
+        .iter()
+        .filter(|(name, _)| !INFORMATIONAL_KEYS.contains(&name.as_str()))
+        .filter_map(|(name, actual_value)| {
+            let expected_value = expected.get(name)?;
+            (expected_value != actual_value).then(|| SettingChange {
+                name: name.clone(),
+                expected: expected_value.clone(),
+                actual: actual_value.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Add the given metadata as text chunks to an encoded PNG image.
+pub(crate) fn with_text_chunks(
+    png_data: &[u8],
+    metadata: &BTreeMap<String, String>,
+) -> Result<Vec<u8>, ScreenshotError> {
+    let mut reader = png::Decoder::new(png_data)
+        .read_info()
+        .map_err(|_| ScreenshotError::Encode)?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut pixels)
+        .map_err(|_| ScreenshotError::Encode)?;
+
+    let mut result = Vec::new();
+    let mut encoder = png::Encoder::new(&mut result, frame.width, frame.height);
+    encoder.set_color(frame.color_type);
+    encoder.set_depth(frame.bit_depth);
+    for (name, value) in metadata {
+        encoder
+            .add_text_chunk(format!("{}{}", KEY_PREFIX, name), value.clone())
+            .map_err(|_| ScreenshotError::Encode)?;
+    }
+    let mut writer = encoder
+        .write_header()
+        .map_err(|_| ScreenshotError::Encode)?;
+    writer
+        .write_image_data(&pixels[..frame.buffer_size()])
+        .map_err(|_| ScreenshotError::Encode)?;
+    writer.finish().map_err(|_| ScreenshotError::Encode)?;
+    Ok(result)
+}
+
+/// Read the metadata written by this crate from the text chunks of the PNG
+/// file. Files without metadata, e.g. snapshots created by an older version
+/// of this crate, return an empty map.
+pub(crate) fn read_metadata(path: &Path) -> Result<BTreeMap<String, String>, ScreenshotError> {
+    let file = std::fs::File::open(path).map_err(|e| ScreenshotError::io(path, e))?;
+    let reader = png::Decoder::new(std::io::BufReader::new(file))
+        .read_info()
+        .map_err(|e| ScreenshotError::io(path, e.into()))?;
+    Ok(reader
+        .info()
+        .uncompressed_latin1_text
+        .iter()
+        .filter_map(|chunk| {
+            let name = chunk.keyword.strip_prefix(KEY_PREFIX)?;
+            Some((name.to_string(), chunk.text.clone()))
+        })
+        .collect())
+}
+
+impl TestBackend {
+    /// The settings used to render a screenshot with the given size in
+    /// pixels, which are stored in the written file.
+    pub(crate) fn snapshot_metadata(&self, surface_size: (u32, u32)) -> BTreeMap<String, String> {
+        let mut metadata = BTreeMap::new();
+        if let Some(egui_version) = egui_version() {
+            metadata.insert(EGUI_VERSION_KEY.to_string(), egui_version.to_string());
+        }
+        metadata.insert(
+            CRATE_VERSION_KEY.to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        );
+        metadata.insert(
+            PIXELS_PER_POINT_KEY.to_string(),
+            self.pixels_per_point.to_string(),
+        );
+        metadata.insert(
+            FRAMES_KEY.to_string(),
+            self.frames_since_screenshot.to_string(),
+        );
+        metadata.insert(COMPARISON_KEY.to_string(), format!("{:?}", self.comparison));
+        metadata.insert(
+            SURFACE_SIZE_KEY.to_string(),
+            format!("{}x{}", surface_size.0, surface_size.1),
+        );
+        if let Some(fingerprint) = &self.font_fingerprint {
+            metadata.insert(FONT_FINGERPRINT_KEY.to_string(), fingerprint.clone());
+        }
+        if let Some(background) = self.background {
+            let [r, g, b, a] = background.to_srgba_unmultiplied();
+            metadata.insert(
+                BACKGROUND_KEY.to_string(),
+                format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+            );
+        }
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_rendering_settings_stored_in_both_are_compared() {
+        let expected = BTreeMap::from([
+            (FRAMES_KEY.to_string(), "2".to_string()),
+            (CRATE_VERSION_KEY.to_string(), "0.3.0".to_string()),
+            (SURFACE_SIZE_KEY.to_string(), "150x100".to_string()),
+            (PIXELS_PER_POINT_KEY.to_string(), "1".to_string()),
+            (COMPARISON_KEY.to_string(), "Exact".to_string()),
+        ]);
+        let actual = BTreeMap::from([
+            (FRAMES_KEY.to_string(), "5".to_string()),
+            (CRATE_VERSION_KEY.to_string(), "0.3.1".to_string()),
+            (
+                COMPARISON_KEY.to_string(),
+                "PerceptualHash { max_distance: 0 }".to_string(),
+            ),
+            (SURFACE_SIZE_KEY.to_string(), "150x100".to_string()),
+            (PIXELS_PER_POINT_KEY.to_string(), "2".to_string()),
+            (FONT_FINGERPRINT_KEY.to_string(), "abc".to_string()),
+        ]);
+        assert_eq!(
+            vec![SettingChange {
+                name: PIXELS_PER_POINT_KEY.to_string(),
+                expected: "1".to_string(),
+                actual: "2".to_string(),
+            }],
+            changed_settings(&expected, &actual)
+        );
+    }
+
+    #[test]
+    fn egui_version_of_this_crate_is_found() {
+        let metadata = serde_json::json!({
+            "packages": [
+                {"name": "egui", "version": "0.26.2", "id": "egui 0.26.2"},
+                {"name": "egui", "version": "0.27.0", "id": "egui 0.27.0"},
+                {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "id": "this crate"
+                },
+            ],
+            "resolve": {
+                "nodes": [
+                    {"id": "other crate", "deps": [{"name": "egui", "pkg": "egui 0.26.2"}]},
+                    {"id": "this crate", "deps": [{"name": "egui", "pkg": "egui 0.27.0"}]},
+                ]
+            }
+        });
+        assert_eq!(Some("0.27.0".to_string()), egui_version_in(&metadata));
+        assert_eq!(None, egui_version_in(&serde_json::json!({})));
+
+        temp_env::with_var(
+            "CARGO_MANIFEST_DIR",
+            Some(env!("CARGO_MANIFEST_DIR")),
+            || {
+                assert!(read_egui_version().is_some());
+            },
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Derive the file names of snapshots from the test function, so they do not
+//! need to be given at each call site.
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::{ScreenshotError, TestBackend};
+
+/// Snapshot files that have already been compared with a derived name in
+/// this process. This is shared by all tests, which run in different threads
+/// of the same process, but a derived name can only be used by one test
+/// function anyway.
+static DERIVED_SNAPSHOTS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// The path of the function this macro is called in, e.g.
+/// `my_crate::tests::main_window`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __function_path {
+    () => {{
+        fn f() {}
+        fn type_name_of<T>(_: T) -> &'static str {
+            ::std::any::type_name::<T>()
+        }
+        let name = type_name_of(f);
+        name.strip_suffix("::f").unwrap_or(name)
+    }};
+}
+
+/// The snapshot file name for the function this macro is called in, e.g.
+/// `my_crate__tests__main_window.png` for the function `main_window` in the
+/// module `tests` of the crate `my_crate`. An optional suffix is appended, so
+/// `snapshot_name!("dark")` becomes `my_crate__tests__main_window-dark.png`.
+///
+/// The crate is part of the name, since each file of an integration test is
+/// its own crate, e.g. `ui` for `tests/ui.rs`. Tests with the same name in
+/// different files therefore use different snapshots.
+///
+/// The name can be used with any function of [`TestBackend`] that takes a
+/// snapshot file name.
+#[macro_export]
+macro_rules! snapshot_name {
+    () => {
+        $crate::derive_snapshot_name($crate::__function_path!(), None)
+    };
+    ($suffix:expr) => {
+        $crate::derive_snapshot_name($crate::__function_path!(), Some($suffix))
+    };
+}
+
+/// Check the rendered view against a snapshot whose file name is derived
+/// from the test function, see [`snapshot_name!`].
+///
+/// ```no_run
+/// # use egui_screenshot_testing::TestBackend;
+/// # let mut backend = TestBackend::builder().build(|_ctx| {});
+/// # let ui = |_ctx: &egui::Context| {};
+/// let result = egui_screenshot_testing::check_screenshot!(backend, ui);
+/// let result = egui_screenshot_testing::check_screenshot!(backend, "clicked", ui);
+/// ```
+///
+/// This uses the default output size and number of frames like
+/// [`TestBackend::check_screenshot`]. Using the same name twice returns
+/// [`ScreenshotError::DuplicateSnapshot`]. The used names are remembered for
+/// the whole test process, so a helper function that calls this macro and is
+/// shared by several tests must pass a different suffix for each test.
+#[macro_export]
+macro_rules! check_screenshot {
+    ($backend:expr, $suffix:expr, $ui:expr $(,)?) => {
+        $backend.check_derived_screenshot(&$crate::snapshot_name!($suffix), $ui)
+    };
+    ($backend:expr, $ui:expr $(,)?) => {
+        $backend.check_derived_screenshot(&$crate::snapshot_name!(), $ui)
+    };
+}
+
+/// Assert that the rendered view is the same as a snapshot whose file name
+/// is derived from the test function, see [`check_screenshot!`].
+///
+/// # Panics
+///
+/// Panics if the actual and expected screenshots are not the same, if the
+/// snapshot file does not exist or if the same name is used twice in the
+/// test process.
+#[macro_export]
+macro_rules! assert_screenshot {
+    ($($args:tt)*) => {
+        if let Err(e) = $crate::check_screenshot!($($args)*) {
+            panic!("{}", e);
+        }
+    };
+}
+
+/// Create the snapshot file name from the path of a function, e.g.
+/// `my_crate::tests::main_window` becomes `my_crate__tests__main_window.png`.
+/// Closures are removed from the path.
+#[doc(hidden)]
+pub fn derive_snapshot_name(function_path: &str, suffix: Option<&str>) -> String {
+    let mut path = function_path;
+    while let Some(outer) = path.strip_suffix("::{{closure}}") {
+        path = outer;
+    }
+    let mut name = path.replace("::", "__");
+    if let Some(suffix) = suffix {
+        name.push('-');
+        name.push_str(suffix);
+    }
+    name.push_str(".png");
+    name
+}
+
+impl TestBackend {
+    /// Check the screenshot with a derived file name, which must not have
+    /// been used before in this process. Used by [`crate::check_screenshot!`].
+    #[doc(hidden)]
+    pub fn check_derived_screenshot(
+        &mut self,
+        expected_file_name: &str,
+        ui: impl FnMut(&egui::Context),
+    ) -> Result<(), ScreenshotError> {
+        let expected_file = self.expected_dir.join(expected_file_name);
+        let is_new = DERIVED_SNAPSHOTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(expected_file.clone());
+        if !is_new {
+            return Err(ScreenshotError::DuplicateSnapshot { expected_file });
+        }
+        self.check_screenshot(expected_file_name, ui)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_from_function_path() {
+        assert_eq!(
+            "my_crate__tests__main_window.png",
+            derive_snapshot_name("my_crate::tests::main_window", None)
+        );
+        assert_eq!(
+            "my_crate__tests__main_window-dark.png",
+            derive_snapshot_name("my_crate::tests::main_window::{{closure}}", Some("dark"))
+        );
+        // Integration tests in different files do not share their snapshots
+        assert_ne!(
+            derive_snapshot_name("a::main_window", None),
+            derive_snapshot_name("b::main_window", None)
+        );
+        assert_eq!(
+            "egui_screenshot_testing__naming__tests__names_from_function_path.png",
+            snapshot_name!()
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Record which snapshot files are referenced by the tests, so snapshot files
+//! that no test uses anymore can be found and deleted.
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{review, ScreenshotError, TestBackend};
+
+/// Environment variable with the path of the file the referenced snapshot
+/// files are appended to.
+pub(crate) const REFERENCES_ENV_VAR: &str = "EGUI_SCREENSHOT_REFERENCES";
+
+/// The absolute path of the given file, with symbolic links resolved if the
+/// file exists, so the same file is always recorded with the same path.
+fn absolute_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| {
+        std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    })
+}
+
+/// Read the snapshot files recorded in the given references file.
+pub fn read_references(
+    references_file: impl AsRef<Path>,
+) -> Result<BTreeSet<PathBuf>, ScreenshotError> {
+    let references_file = references_file.as_ref();
+    let content = std::fs::read_to_string(references_file)
+        .map_err(|e| ScreenshotError::io(references_file, e))?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| absolute_path(Path::new(line)))
+        .collect())
+}
+
+/// Remove all references from the given references file, which must be done
+/// before running the tests, since the file is only appended to.
+pub fn reset_references(references_file: impl AsRef<Path>) -> Result<(), ScreenshotError> {
+    let references_file = references_file.as_ref();
+    std::fs::write(references_file, "").map_err(|e| ScreenshotError::io(references_file, e))
+}
+
+/// Find all snapshot files (`*.png`) in the given directory and its
+/// subdirectories that are not contained in the `references`, sorted by
+/// their path. Pending snapshots and files that are only reachable through
+/// symbolic links pointing outside of the directory are ignored.
+pub fn find_orphaned_snapshots(
+    dir: impl AsRef<Path>,
+    references: &BTreeSet<PathBuf>,
+) -> Result<Vec<PathBuf>, ScreenshotError> {
+    let dir = dir.as_ref();
+    let absolute_dir = absolute_path(dir);
+    let mut result = Vec::new();
+    review::visit_files(dir, &mut |path| {
+        let is_png = path.extension().is_some_and(|e| e == "png");
+        let absolute_file = absolute_path(&path);
+        if is_png
+            && absolute_file.starts_with(&absolute_dir)
+            && !review::is_pending_file(&path)
+            && !references.contains(&absolute_file)
+        {
+            result.push(path);
+        }
+    })?;
+    result.sort();
+    Ok(result)
+}
+
+impl TestBackend {
+    /// Append the path of each snapshot file that is compared to the given
+    /// file, one path per line.
+    ///
+    /// The environment variable `EGUI_SCREENSHOT_REFERENCES` overrides this
+    /// setting. After running all tests, the file can be passed to the
+    /// `egui-screenshot-orphans` binary or [`crate::find_orphaned_snapshots`]
+    /// to find snapshot files that no test referenced.
+    ///
+    /// The file is never truncated, so references of earlier test runs are
+    /// kept. Reset it before running the tests with
+    /// `egui-screenshot-orphans --reset REFERENCES_FILE` or
+    /// [`crate::reset_references`].
+    pub fn with_references_file(mut self, references_file: impl Into<PathBuf>) -> Self {
+        self.references_file = Some(references_file.into());
+        self
+    }
+
+    /// The snapshot files that have been compared by this backend.
+    pub fn touched_snapshots(&self) -> &[PathBuf] {
+        &self.touched_snapshots
+    }
+
+    /// Remember that the given snapshot file is referenced by a test and
+    /// append it to the references file, if there is one.
+    pub(crate) fn record_reference(&mut self, expected_file: &Path) -> Result<(), ScreenshotError> {
+        if !self.touched_snapshots.iter().any(|f| f == expected_file) {
+            self.touched_snapshots.push(expected_file.to_path_buf());
+        }
+
+        let references_file = std::env::var_os(REFERENCES_ENV_VAR)
+            .map(PathBuf::from)
+            .or_else(|| self.references_file.clone());
+        if let Some(references_file) = references_file {
+            if let Some(parent) = references_file.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| ScreenshotError::io(parent, e))?;
+            }
+            // Tests run in parallel, so the whole line is written at once
+            let line = format!("{}\n", absolute_path(expected_file).display());
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&references_file)
+                .and_then(|mut file| file.write_all(line.as_bytes()))
+                .map_err(|e| ScreenshotError::io(&references_file, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreferenced_snapshots_are_orphaned() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub_dir = dir.path().join("sub");
+        std::fs::create_dir_all(&sub_dir).unwrap();
+        for file in ["used.png", "unused.png", "unused.new.png", "notes.txt"] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+        std::fs::write(sub_dir.join("unused.png"), "").unwrap();
+
+        let references_file = dir.path().join("references.txt");
+        std::fs::write(
+            &references_file,
+            format!("{}\n", dir.path().join("used.png").display()),
+        )
+        .unwrap();
+
+        let references = read_references(&references_file).unwrap();
+        let orphans = find_orphaned_snapshots(dir.path(), &references).unwrap();
+        assert_eq!(
+            vec![
+                dir.path().join("sub/unused.png"),
+                dir.path().join("unused.png")
+            ],
+            orphans
+        );
+
+        reset_references(&references_file).unwrap();
+        assert!(read_references(&references_file).unwrap().is_empty());
+    }
+}
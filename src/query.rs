@@ -0,0 +1,177 @@
+//! Find widgets in the accessibility tree of the last rendered frame and
+//! interact with them.
+use egui::accesskit::{self, Action, ActionRequest, Checked, NodeId, Role};
+use egui::{Event, Rect};
+
+use crate::{ScreenshotError, TestBackend};
+
+/// Describes which widgets to find with [`TestBackend::find_node`].
+///
+/// All given criteria must match. An empty query matches all widgets.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    label: Option<String>,
+    id: Option<egui::Id>,
+    role: Option<Role>,
+}
+
+impl Query {
+    /// Find widgets which have exactly the given label, e.g. the text of a
+    /// button.
+    pub fn label(label: impl Into<String>) -> Self {
+        Query {
+            label: Some(label.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Find the widget with the given egui [`egui::Id`].
+    pub fn id(id: impl Into<egui::Id>) -> Self {
+        Query {
+            id: Some(id.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Only find widgets with the given accessibility role.
+    pub fn with_role(mut self, role: Role) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    fn matches(&self, id: NodeId, node: &accesskit::Node) -> bool {
+        if let Some(label) = &self.label {
+            if node.name() != Some(label.as_str()) {
+                return false;
+            }
+        }
+        if let Some(expected_id) = self.id {
+            if id != NodeId(expected_id.value()) {
+                return false;
+            }
+        }
+        if let Some(role) = self.role {
+            if node.role() != role {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A widget found in the accessibility tree.
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub id: NodeId,
+    pub role: Role,
+    pub label: Option<String>,
+    pub value: Option<String>,
+    /// The area of the widget in egui points.
+    pub rect: Option<Rect>,
+    pub focused: bool,
+    pub disabled: bool,
+    pub checked: Option<Checked>,
+}
+
+impl TestBackend {
+    /// Find all widgets matching the query in the last rendered frame.
+    ///
+    /// The widgets are sorted by their position, from top to bottom and left
+    /// to right.
+    pub fn find_all_nodes(&self, query: &Query) -> Vec<Node> {
+        let Some(tree) = &self.accesskit_tree else {
+            return Vec::new();
+        };
+        let mut result: Vec<Node> = tree
+            .nodes
+            .iter()
+            .filter(|(id, node)| query.matches(*id, node))
+            .map(|(id, node)| Node {
+                id: *id,
+                role: node.role(),
+                label: node.name().map(str::to_string),
+                value: node.value().map(str::to_string),
+                rect: node.bounds().map(|b| {
+                    Rect::from_min_max(
+                        [b.x0 as f32, b.y0 as f32].into(),
+                        [b.x1 as f32, b.y1 as f32].into(),
+                    )
+                }),
+                focused: tree.focus == *id,
+                disabled: node.is_disabled(),
+                checked: node.checked(),
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            let a = a.rect.map(|r| (r.min.y, r.min.x)).unwrap_or_default();
+            let b = b.rect.map(|r| (r.min.y, r.min.x)).unwrap_or_default();
+            a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        result
+    }
+
+    /// Find the first widget matching the query in the last rendered frame.
+    ///
+    /// Frames are only rendered when calling e.g. [`Self::run_frames`], so
+    /// make sure to render at least one frame before searching for widgets.
+    pub fn find_node(&self, query: &Query) -> Option<Node> {
+        self.find_all_nodes(query).into_iter().next()
+    }
+
+    /// Queue a click in the center of the widget matching the query.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no widget with a known position matches the query.
+    pub fn click_node(&mut self, query: &Query) {
+        if let Err(e) = self.try_click_node(query) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Queue a click in the center of the widget matching the query.
+    ///
+    /// This works like [`Self::click_node`], but returns
+    /// [`ScreenshotError::NodeNotFound`] instead of panicking if no widget
+    /// with a known position matches the query.
+    pub fn try_click_node(&mut self, query: &Query) -> Result<(), ScreenshotError> {
+        let rect = self
+            .find_node(query)
+            .and_then(|node| node.rect)
+            .ok_or_else(|| ScreenshotError::NodeNotFound {
+                query: query.clone(),
+            })?;
+        self.click(rect.center());
+        Ok(())
+    }
+
+    /// Queue focusing the widget matching the query.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no widget matches the query.
+    pub fn focus_node(&mut self, query: &Query) {
+        let node = self.expect_node(query);
+        self.queue_input(vec![Event::AccessKitActionRequest(ActionRequest {
+            action: Action::Focus,
+            target: node.id,
+            data: None,
+        })]);
+    }
+
+    /// Queue focusing the widget matching the query and typing the given
+    /// text into it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no widget matches the query.
+    pub fn type_into_node(&mut self, query: &Query, text: impl Into<String>) {
+        self.focus_node(query);
+        self.type_text(text);
+    }
+
+    fn expect_node(&self, query: &Query) -> Node {
+        self.find_node(query)
+            .unwrap_or_else(|| panic!("No widget matching {:?} found", query))
+    }
+}
@@ -0,0 +1,330 @@
+//! Collect the results of all screenshot comparisons in a results file or a
+//! report directory and create a static HTML page from them.
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{diff, Comparison, DiffStats, ScreenshotError, TestBackend};
+
+/// Environment variable with the directory the results are written to.
+pub(crate) const REPORT_DIR_ENV_VAR: &str = "EGUI_SCREENSHOT_REPORT_DIR";
+/// Environment variable with the file the results are appended to.
+pub(crate) const RESULTS_ENV_VAR: &str = "EGUI_SCREENSHOT_RESULTS";
+
+/// File in the report directory each result is appended to, one JSON object
+/// per line.
+const RESULTS_FILE: &str = "results.jsonl";
+/// Directory inside the report directory the images are copied to.
+const IMAGES_DIR: &str = "images";
+/// The generated HTML page.
+const HTML_FILE: &str = "index.html";
+
+/// Counter that makes the names of the copied images unique within a
+/// process.
+static NEXT_ENTRY: AtomicUsize = AtomicUsize::new(0);
+
+/// The result of a single screenshot comparison.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReportEntry {
+    /// Name of the test, which is the name of the thread the comparison was
+    /// executed in.
+    pub test: String,
+    /// The snapshot file.
+    pub snapshot: PathBuf,
+    /// The comparison used, e.g. `{"mode": "exact"}` in the JSON objects.
+    pub comparison: Comparison,
+    /// Time in seconds since the backend was created or the previous
+    /// screenshot was compared, which includes rendering the frames.
+    pub duration: f64,
+    pub passed: bool,
+    /// Description of the failure.
+    pub message: Option<String>,
+    /// Statistics about the differing pixels, if the images have the same
+    /// size.
+    pub stats: Option<DiffStats>,
+    /// Copy of the snapshot, relative to the report directory. The images are
+    /// only set for results in a report directory.
+    pub expected_image: Option<String>,
+    /// Copy of the actual screenshot, relative to the report directory. Only
+    /// set if the comparison failed.
+    pub actual_image: Option<String>,
+    /// Copy of the highlighted difference image, relative to the report
+    /// directory.
+    pub diff_image: Option<String>,
+}
+
+/// Read all results from a results file or the results file of a report
+/// directory.
+pub fn read_report_entries(path: impl AsRef<Path>) -> Result<Vec<ReportEntry>, ScreenshotError> {
+    let path = path.as_ref();
+    let results_file = if path.is_dir() {
+        path.join(RESULTS_FILE)
+    } else {
+        path.to_path_buf()
+    };
+    let content = std::fs::read_to_string(&results_file)
+        .map_err(|e| ScreenshotError::io(&results_file, e))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| ScreenshotError::io(&results_file, e.into()))
+        })
+        .collect()
+}
+
+/// Append the entry as a single line to the results file.
+fn append_entry(results_file: &Path, entry: &ReportEntry) -> Result<(), ScreenshotError> {
+    if let Some(parent) = results_file.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| ScreenshotError::io(parent, e))?;
+    }
+    let mut line =
+        serde_json::to_string(entry).map_err(|e| ScreenshotError::io(results_file, e.into()))?;
+    line.push('\n');
+    // Tests run in parallel, so the whole line is written at once
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(results_file)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| ScreenshotError::io(results_file, e))
+}
+
+/// Create an `index.html` file in the report directory that lists all
+/// results, failed ones first. Returns the path of the created file.
+pub fn write_html_report(report_dir: impl AsRef<Path>) -> Result<PathBuf, ScreenshotError> {
+    let report_dir = report_dir.as_ref();
+    let mut entries = read_report_entries(report_dir)?;
+    entries.sort_by(|a, b| (a.passed, &a.test, &a.snapshot).cmp(&(b.passed, &b.test, &b.snapshot)));
+    let html_file = report_dir.join(HTML_FILE);
+    std::fs::write(&html_file, html(&entries)).map_err(|e| ScreenshotError::io(&html_file, e))?;
+    Ok(html_file)
+}
+
+fn html(entries: &[ReportEntry]) -> String {
+    let failed = entries.iter().filter(|e| !e.passed).count();
+    let mut result = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Screenshot test report</title>\n<style>\n\
+         body { font-family: sans-serif; }\n\
+         table { border-collapse: collapse; }\n\
+         td, th { border: 1px solid #ccc; padding: 4px; text-align: left; vertical-align: top; }\n\
+         img { max-width: 200px; max-height: 200px; }\n\
+         .passed { color: #080; }\n\
+         .failed { color: #c00; font-weight: bold; }\n\
+         </style>\n</head>\n<body>\n<h1>Screenshot test report</h1>\n",
+    );
+    result.push_str(&format!(
+        "<p>{} passed, {} failed</p>\n",
+        entries.len() - failed,
+        failed
+    ));
+    result.push_str(
+        "<table>\n<tr><th>Status</th><th>Test</th><th>Snapshot</th><th>Expected</th>\
+         <th>Actual</th><th>Difference</th><th>Details</th></tr>\n",
+    );
+    for entry in entries {
+        let (class, status) = if entry.passed {
+            ("passed", "passed")
+        } else {
+            ("failed", "failed")
+        };
+        let details = match (&entry.message, &entry.stats) {
+            (Some(message), _) => escape(message),
+            (None, Some(stats)) => escape(&stats.to_string()),
+            (None, None) => String::new(),
+        };
+        result.push_str(&format!(
+            "<tr><td class=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+             <td>{}</td><td>{}</td></tr>\n",
+            class,
+            status,
+            escape(&entry.test),
+            escape(&entry.snapshot.to_string_lossy()),
+            thumbnail(&entry.expected_image),
+            thumbnail(&entry.actual_image),
+            thumbnail(&entry.diff_image),
+            details
+        ));
+    }
+    result.push_str("</table>\n</body>\n</html>\n");
+    result
+}
+
+/// A thumbnail that links to the full image.
+fn thumbnail(image: &Option<String>) -> String {
+    match image {
+        Some(image) => {
+            let image = escape(image);
+            format!("<a href=\"{image}\"><img src=\"{image}\" loading=\"lazy\"></a>")
+        }
+        None => String::new(),
+    }
+}
+
+/// Escape the text for HTML and XML documents. Line breaks and tabs are
+/// written as character references, so they are kept in attribute values.
+/// Other control characters, e.g. the color codes in panic messages, are not
+/// allowed in XML and replaced with U+FFFD.
+pub(crate) fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\t' | '\n' | '\r' => result.push_str(&format!("&#{};", c as u32)),
+            '\u{fffe}' | '\u{ffff}' => result.push(char::REPLACEMENT_CHARACTER),
+            c if c.is_control() => result.push(char::REPLACEMENT_CHARACTER),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+impl TestBackend {
+    /// Append the result of each screenshot comparison to the given report
+    /// directory, together with copies of the snapshot, actual and
+    /// difference images.
+    ///
+    /// The environment variable `EGUI_SCREENSHOT_REPORT_DIR` overrides this
+    /// setting. Several tests can share the same report directory. After
+    /// running all tests, create an HTML page from the results with
+    /// [`crate::write_html_report`] or the `egui-screenshot-report` binary.
+    pub fn with_report_dir(mut self, report_dir: impl Into<PathBuf>) -> Self {
+        self.report_dir = Some(report_dir.into());
+        self
+    }
+
+    /// Append a JSON object describing the result of each screenshot
+    /// comparison to the given file, one object per line.
+    ///
+    /// Unlike [`Self::with_report_dir`], no images are copied. The
+    /// environment variable `EGUI_SCREENSHOT_RESULTS` overrides this setting.
+    /// After running all tests, the results can be converted to JUnit XML
+    /// with [`crate::write_junit_report`] or the `egui-screenshot-junit`
+    /// binary.
+    pub fn with_results_file(mut self, results_file: impl Into<PathBuf>) -> Self {
+        self.results_file = Some(results_file.into());
+        self
+    }
+
+    /// Add the result of comparing the snapshot with the given file name to
+    /// the results file and the report directory, if configured.
+    pub(crate) fn report_result(
+        &self,
+        expected_file_name: &str,
+        result: &Result<(), ScreenshotError>,
+        duration: Duration,
+    ) -> Result<(), ScreenshotError> {
+        let results_file = std::env::var_os(RESULTS_ENV_VAR)
+            .map(PathBuf::from)
+            .or_else(|| self.results_file.clone());
+        let report_dir = std::env::var_os(REPORT_DIR_ENV_VAR)
+            .map(PathBuf::from)
+            .or_else(|| self.report_dir.clone());
+        if results_file.is_none() && report_dir.is_none() {
+            return Ok(());
+        }
+
+        let expected_file = self.expected_dir.join(expected_file_name);
+        let mut entry = ReportEntry {
+            test: std::thread::current()
+                .name()
+                .unwrap_or("unknown")
+                .to_string(),
+            snapshot: expected_file.clone(),
+            comparison: self.comparison,
+            duration: duration.as_secs_f64(),
+            passed: result.is_ok(),
+            message: None,
+            stats: None,
+            expected_image: None,
+            actual_image: None,
+            diff_image: None,
+        };
+        if let Err(error) = result {
+            entry.message = Some(error.to_string());
+            entry.stats = error.diff_stats().cloned();
+        }
+        if let Some(results_file) = results_file {
+            append_entry(&results_file, &entry)?;
+        }
+        let Some(report_dir) = report_dir else {
+            return Ok(());
+        };
+
+        let images_dir = report_dir.join(IMAGES_DIR);
+        std::fs::create_dir_all(&images_dir).map_err(|e| ScreenshotError::io(&images_dir, e))?;
+        let id = format!(
+            "{}-{}-{}",
+            std::process::id(),
+            NEXT_ENTRY.fetch_add(1, Ordering::Relaxed),
+            expected_file_name.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+        );
+        let copy_image = |source: &Path, kind: &str| -> Result<Option<String>, ScreenshotError> {
+            if !source.is_file() {
+                return Ok(None);
+            }
+            let file_name = format!("{}.{}.png", id, kind);
+            let target = images_dir.join(&file_name);
+            std::fs::copy(source, &target).map_err(|e| ScreenshotError::io(&target, e))?;
+            Ok(Some(format!("{}/{}", IMAGES_DIR, file_name)))
+        };
+
+        entry.expected_image = copy_image(&expected_file, "expected")?;
+        if let Err(error) = result {
+            let actual_file = self.actual_dir.join(expected_file_name);
+            entry.actual_image = copy_image(&actual_file, "actual")?;
+            if error.is_mismatch() {
+                let (diff_file, _) = diff::diff_file_paths(&actual_file);
+                entry.diff_image = copy_image(&diff_file, "diff")?;
+            }
+        }
+        append_entry(&report_dir.join(RESULTS_FILE), &entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_lists_failed_entries_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = |test: &str, passed: bool| ReportEntry {
+            test: test.to_string(),
+            snapshot: PathBuf::from("expected/main.png"),
+            comparison: Comparison::Exact,
+            duration: 0.1,
+            passed,
+            message: (!passed).then(|| "<different>".to_string()),
+            stats: None,
+            expected_image: Some("images/main.expected.png".to_string()),
+            actual_image: None,
+            diff_image: None,
+        };
+        let results_file = dir.path().join(RESULTS_FILE);
+        append_entry(&results_file, &entry("a_test", true)).unwrap();
+        append_entry(&results_file, &entry("b_test", false)).unwrap();
+        assert_eq!(2, read_report_entries(dir.path()).unwrap().len());
+
+        let html = std::fs::read_to_string(write_html_report(dir.path()).unwrap()).unwrap();
+        assert!(html.contains("1 passed, 1 failed"));
+        assert!(html.contains("&lt;different&gt;"));
+        assert!(html.find("b_test").unwrap() < html.find("a_test").unwrap());
+    }
+
+    #[test]
+    fn escape_control_characters() {
+        assert_eq!(
+            "&lt;a&gt; &amp; &quot;b&quot;&#10;\u{fffd}[31mred\u{fffd}[0m",
+            escape("<a> & \"b\"\n\u{1b}[31mred\u{1b}[0m")
+        );
+        assert_eq!("tab&#9;cr&#13;nul\u{fffd}", escape("tab\tcr\rnul\0"));
+    }
+}
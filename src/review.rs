@@ -0,0 +1,250 @@
+//! Pending snapshots are written next to the snapshot file instead of
+//! replacing it, so they can be reviewed and accepted or rejected later.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::metadata::{self, PENDING_KEY};
+use crate::{read_image, Comparison, Mismatch, ScreenshotError, TestBackend};
+
+/// The part of the file stem that marks a pending snapshot, e.g.
+/// `main.new.png` is the pending snapshot for `main.png`.
+const PENDING_MARKER: &str = ".new";
+
+/// The path of the pending snapshot that belongs to the given snapshot file.
+pub(crate) fn pending_file_path(expected_file: &Path) -> PathBuf {
+    let stem = expected_file
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let file_name = match expected_file.extension() {
+        Some(extension) => format!("{stem}{PENDING_MARKER}.{}", extension.to_string_lossy()),
+        None => format!("{stem}{PENDING_MARKER}"),
+    };
+    expected_file.with_file_name(file_name)
+}
+
+/// The path of the snapshot file the given pending snapshot belongs to, or
+/// `None` if the file name is not the one of a pending snapshot.
+fn expected_file_path(pending_file: &Path) -> Option<PathBuf> {
+    if pending_file.extension()? != "png" {
+        return None;
+    }
+    let stem = pending_file.file_stem()?.to_str()?;
+    let stem = stem.strip_suffix(PENDING_MARKER)?;
+    if stem.is_empty() {
+        return None;
+    }
+    Some(pending_file.with_file_name(format!("{stem}.png")))
+}
+
+/// The snapshot file the given file is the pending snapshot of. A `*.new.png`
+/// file is only a pending snapshot if the snapshot file exists or if it was
+/// written as pending snapshot by this crate, so snapshots that happen to
+/// be named like this are not mistaken for pending ones.
+fn pending_snapshot_of(path: &Path) -> Option<PathBuf> {
+    let expected_file = expected_file_path(path)?;
+    let is_marked =
+        || metadata::read_metadata(path).is_ok_and(|metadata| metadata.contains_key(PENDING_KEY));
+    (expected_file.is_file() || is_marked()).then_some(expected_file)
+}
+
+/// Remove the pending snapshot of the given snapshot file, if there is one.
+pub(crate) fn remove_pending_snapshot(expected_file: &Path) -> std::io::Result<()> {
+    let pending_file = pending_file_path(expected_file);
+    if pending_file.is_file() {
+        std::fs::remove_file(pending_file)?;
+    }
+    Ok(())
+}
+
+/// A screenshot that was written next to the snapshot file because it was
+/// missing or did not match, and that has not been reviewed yet.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PendingSnapshot {
+    /// The file with the pending screenshot, e.g. `main.new.png`.
+    pub pending_file: PathBuf,
+    /// The snapshot file that is replaced when accepting, e.g. `main.png`.
+    pub expected_file: PathBuf,
+}
+
+impl PendingSnapshot {
+    /// Whether there is no snapshot file yet.
+    pub fn is_new(&self) -> bool {
+        !self.expected_file.is_file()
+    }
+
+    /// Compare the pending screenshot with the existing snapshot pixel by
+    /// pixel. Returns `None` if both are equal or there is no snapshot yet.
+    pub fn mismatch(&self) -> Result<Option<Mismatch>, ScreenshotError> {
+        if self.is_new() {
+            return Ok(None);
+        }
+        let expected = read_image(&self.expected_file)?;
+        let pending = read_image(&self.pending_file)?;
+        Ok(Comparison::Exact.compare(&expected, &pending).err())
+    }
+
+    /// Replace the snapshot file with the pending screenshot.
+    pub fn accept(&self) -> Result<(), ScreenshotError> {
+        let mut metadata = metadata::read_metadata(&self.pending_file)?;
+        metadata.remove(PENDING_KEY);
+        let png_data = std::fs::read(&self.pending_file)
+            .map_err(|e| ScreenshotError::io(&self.pending_file, e))?;
+        let png_data = metadata::with_text_chunks(&png_data, &metadata)?;
+        std::fs::write(&self.expected_file, png_data)
+            .map_err(|e| ScreenshotError::io(&self.expected_file, e))?;
+        self.reject()
+    }
+
+    /// Delete the pending screenshot and keep the snapshot file.
+    pub fn reject(&self) -> Result<(), ScreenshotError> {
+        std::fs::remove_file(&self.pending_file)
+            .map_err(|e| ScreenshotError::io(&self.pending_file, e))
+    }
+}
+
+/// Find all pending snapshots in the given directory and its
+/// subdirectories, sorted by their path. Hidden directories and `target`
+/// directories are skipped.
+///
+/// Pending snapshots are `*.new.png` files next to the snapshot file they
+/// replace. If there is no such snapshot file yet, only files written as
+/// pending snapshot by [`crate::UpdatePolicy::Pending`] are found.
+pub fn find_pending_snapshots(
+    dir: impl AsRef<Path>,
+) -> Result<Vec<PendingSnapshot>, ScreenshotError> {
+    let mut result = Vec::new();
+    visit_files(dir.as_ref(), &mut |path| {
+        if let Some(expected_file) = pending_snapshot_of(&path) {
+            result.push(PendingSnapshot {
+                pending_file: path,
+                expected_file,
+            });
+        }
+    })?;
+    result.sort();
+    Ok(result)
+}
+
+/// Call `visit` for all files in the given directory and its
+/// subdirectories. Hidden directories and `target` directories are skipped.
+pub(crate) fn visit_files(
+    dir: &Path,
+    visit: &mut impl FnMut(PathBuf),
+) -> Result<(), ScreenshotError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| ScreenshotError::io(dir, e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| ScreenshotError::io(dir, e))?.path();
+        if path.is_dir() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !name.starts_with('.') && name != "target" {
+                visit_files(&path, visit)?;
+            }
+        } else {
+            visit(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the file is a pending snapshot.
+pub(crate) fn is_pending_file(path: &Path) -> bool {
+    pending_snapshot_of(path).is_some()
+}
+
+impl TestBackend {
+    /// Write the actual screenshot as pending snapshot next to the snapshot
+    /// file. The written file is marked as pending in its metadata.
+    pub(crate) fn write_pending_snapshot(
+        &self,
+        output_file: &Path,
+        png_data: &[u8],
+        metadata: &BTreeMap<String, String>,
+    ) -> Result<(), ScreenshotError> {
+        let pending_file = pending_file_path(output_file);
+        let mut metadata = metadata.clone();
+        metadata.insert(PENDING_KEY.to_string(), "true".to_string());
+        let png_data = metadata::with_text_chunks(png_data, &metadata)?;
+        self.write_snapshot(&pending_file, &png_data)?;
+        eprintln!(
+            "Pending snapshot written to {:#?}, review it with `egui-screenshot-review`",
+            pending_file
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a PNG file with a single pixel of the given color, optionally
+    /// marked as pending snapshot.
+    fn write_png(path: &Path, color: [u8; 4], is_pending: bool) {
+        let image = image::RgbaImage::from_pixel(1, 1, image::Rgba(color));
+        let mut png_data = std::io::Cursor::new(Vec::new());
+        image
+            .write_to(&mut png_data, image::ImageOutputFormat::Png)
+            .unwrap();
+        let mut metadata = BTreeMap::new();
+        if is_pending {
+            metadata.insert(PENDING_KEY.to_string(), "true".to_string());
+        }
+        let png_data = metadata::with_text_chunks(png_data.get_ref(), &metadata).unwrap();
+        std::fs::write(path, png_data).unwrap();
+    }
+
+    #[test]
+    fn pending_file_names() {
+        let pending = pending_file_path(Path::new("expected/sub/main.png"));
+        assert_eq!(Path::new("expected/sub/main.new.png"), pending);
+        assert_eq!(
+            Some(PathBuf::from("expected/sub/main.png")),
+            expected_file_path(&pending)
+        );
+        assert_eq!(None, expected_file_path(Path::new("expected/main.png")));
+        assert_eq!(None, expected_file_path(Path::new("expected/.new.png")));
+        assert_eq!(None, expected_file_path(Path::new("expected/main.new.txt")));
+    }
+
+    #[test]
+    fn find_and_accept_pending_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub_dir = dir.path().join("sub");
+        std::fs::create_dir_all(&sub_dir).unwrap();
+        write_png(&dir.path().join("a.png"), [255, 0, 0, 255], false);
+        write_png(&dir.path().join("a.new.png"), [0, 255, 0, 255], false);
+        write_png(&sub_dir.join("b.new.png"), [0, 0, 255, 255], true);
+        // Neither a snapshot file nor marked as pending
+        write_png(&sub_dir.join("c.new.png"), [0, 0, 255, 255], false);
+        std::fs::write(dir.path().join("d.new.txt"), "new").unwrap();
+        std::fs::write(dir.path().join("d.txt"), "old").unwrap();
+
+        let pending = find_pending_snapshots(dir.path()).unwrap();
+        assert_eq!(2, pending.len());
+        assert_eq!(dir.path().join("a.png"), pending[0].expected_file);
+        assert!(!pending[0].is_new());
+        assert!(pending[0].mismatch().unwrap().is_some());
+        assert_eq!(sub_dir.join("b.png"), pending[1].expected_file);
+        assert!(pending[1].is_new());
+
+        pending[0].reject().unwrap();
+        pending[1].accept().unwrap();
+        assert_eq!(
+            image::Rgba([255, 0, 0, 255]),
+            read_image(&dir.path().join("a.png")).unwrap().to_rgba8()[(0, 0)]
+        );
+        assert!(!dir.path().join("a.new.png").exists());
+        assert!(!sub_dir.join("b.new.png").exists());
+        let accepted = sub_dir.join("b.png");
+        assert_eq!(
+            image::Rgba([0, 0, 255, 255]),
+            read_image(&accepted).unwrap().to_rgba8()[(0, 0)]
+        );
+        assert!(!metadata::read_metadata(&accepted)
+            .unwrap()
+            .contains_key(PENDING_KEY));
+        assert!(find_pending_snapshots(dir.path()).unwrap().is_empty());
+    }
+}
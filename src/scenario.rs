@@ -0,0 +1,203 @@
+//! Scenarios chain several interactions and snapshots in a single test.
+use std::fmt::Display;
+use std::time::Duration;
+
+use egui::{Key, Modifiers, Pos2};
+
+use crate::{Query, ScreenshotError, TestBackend};
+
+#[derive(Debug)]
+enum Step {
+    Frames(usize),
+    UntilStable(usize),
+    Wait(Duration),
+    Click(Pos2),
+    ClickNode(Query),
+    TypeText(String),
+    KeyChord(Modifiers, Key),
+    Snapshot(String),
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Frames(n) => write!(f, "render {} frames", n),
+            Step::UntilStable(max_frames) => {
+                write!(f, "render until stable (max {} frames)", max_frames)
+            }
+            Step::Wait(duration) => write!(f, "wait {:?}", duration),
+            Step::Click(pos) => write!(f, "click at {:?}", pos),
+            Step::ClickNode(query) => write!(f, "click on {:?}", query),
+            Step::TypeText(text) => write!(f, "type {:?}", text),
+            Step::KeyChord(modifiers, key) => write!(f, "press {:?} {:?}", modifiers, key),
+            Step::Snapshot(name) => write!(f, "snapshot {:?}", name),
+        }
+    }
+}
+
+/// A step of a [`Scenario`] that did not succeed.
+#[derive(Debug)]
+pub struct StepFailure {
+    /// Index of the step, starting with 1.
+    pub step: usize,
+    /// Human readable description of the step.
+    pub description: String,
+    pub error: ScreenshotError,
+}
+
+impl Display for StepFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Step {} ({}): {}",
+            self.step, self.description, self.error
+        )
+    }
+}
+
+/// A sequence of steps that are executed on the same application state.
+///
+/// Create a scenario with [`TestBackend::scenario`], add steps by chaining
+/// the functions and execute it with [`Self::assert`] or [`Self::check`].
+/// The egui memory and the application state are kept across all steps, and
+/// each [`Self::snapshot`] step is compared with its own snapshot file.
+pub struct Scenario<'a, F> {
+    backend: &'a mut TestBackend,
+    output_size: (i32, i32),
+    ui: F,
+    steps: Vec<Step>,
+}
+
+impl<F> Scenario<'_, F>
+where
+    F: FnMut(&egui::Context),
+{
+    /// Render the given number of frames.
+    pub fn frames(mut self, n: usize) -> Self {
+        self.steps.push(Step::Frames(n));
+        self
+    }
+
+    /// Render frames until the user interface is stable, see
+    /// [`TestBackend::assert_screenshot_when_stable`].
+    pub fn until_stable(mut self, max_frames: usize) -> Self {
+        self.steps.push(Step::UntilStable(max_frames));
+        self
+    }
+
+    /// Render frames until the virtual clock advanced by the given duration.
+    pub fn wait(mut self, duration: Duration) -> Self {
+        self.steps.push(Step::Wait(duration));
+        self
+    }
+
+    /// Click with the primary pointer button at the given position.
+    pub fn click(mut self, pos: impl Into<Pos2>) -> Self {
+        self.steps.push(Step::Click(pos.into()));
+        self
+    }
+
+    /// Click on the widget matching the query. The widget must have been
+    /// rendered in a previous step.
+    pub fn click_node(mut self, query: Query) -> Self {
+        self.steps.push(Step::ClickNode(query));
+        self
+    }
+
+    /// Type the given text.
+    pub fn type_text(mut self, text: impl Into<String>) -> Self {
+        self.steps.push(Step::TypeText(text.into()));
+        self
+    }
+
+    /// Press the given key.
+    pub fn key_press(self, key: Key) -> Self {
+        self.key_chord(Modifiers::NONE, key)
+    }
+
+    /// Press the given key while holding down the modifiers.
+    pub fn key_chord(mut self, modifiers: Modifiers, key: Key) -> Self {
+        self.steps.push(Step::KeyChord(modifiers, key));
+        self
+    }
+
+    /// Render a frame and compare it with the snapshot file with the given
+    /// name.
+    pub fn snapshot(mut self, expected_file_name: impl Into<String>) -> Self {
+        self.steps.push(Step::Snapshot(expected_file_name.into()));
+        self
+    }
+
+    /// Execute all steps and return the failed ones.
+    ///
+    /// All steps are executed, even if a previous snapshot did not match, so
+    /// there is an actual screenshot for each failed snapshot.
+    pub fn check(mut self) -> Result<(), Vec<StepFailure>> {
+        let steps = std::mem::take(&mut self.steps);
+        let mut failures = Vec::new();
+        for (idx, step) in steps.into_iter().enumerate() {
+            if let Err(error) = self.execute(&step) {
+                failures.push(StepFailure {
+                    step: idx + 1,
+                    description: step.to_string(),
+                    error,
+                });
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Execute all steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the steps failed, listing all failed steps.
+    pub fn assert(self) {
+        if let Err(failures) = self.check() {
+            let messages: Vec<_> = failures.iter().map(|f| f.to_string()).collect();
+            panic!("Scenario failed:\n{}", messages.join("\n"));
+        }
+    }
+
+    fn execute(&mut self, step: &Step) -> Result<(), ScreenshotError> {
+        let output_size = self.output_size;
+        let ui = &mut self.ui;
+        match step {
+            Step::Frames(n) => self.backend.run_frames(output_size, *n, ui),
+            Step::UntilStable(max_frames) => {
+                self.backend
+                    .render_until_stable(output_size, *max_frames, ui)?;
+            }
+            Step::Wait(duration) => self.backend.run_for(output_size, *duration, ui),
+            Step::Click(pos) => self.backend.click(*pos),
+            Step::ClickNode(query) => self.backend.try_click_node(query)?,
+            Step::TypeText(text) => self.backend.type_text(text.clone()),
+            Step::KeyChord(modifiers, key) => self.backend.key_chord(*modifiers, *key),
+            Step::Snapshot(name) => {
+                self.backend
+                    .check_screenshot_after_n_frames(name, output_size, 1, ui)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TestBackend {
+    /// Create a new [`Scenario`] that renders the user interface created by
+    /// `ui` with the given `output_size` in egui points.
+    pub fn scenario<F>(&mut self, output_size: (i32, i32), ui: F) -> Scenario<'_, F>
+    where
+        F: FnMut(&egui::Context),
+    {
+        Scenario {
+            backend: self,
+            output_size,
+            ui,
+            steps: Vec::new(),
+        }
+    }
+}
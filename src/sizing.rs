@@ -0,0 +1,72 @@
+//! Control the size of the rendered screenshots.
+use crate::{ScreenshotError, TestBackend};
+
+impl TestBackend {
+    /// Assert that the rendered view is the same as the snapshot, using a
+    /// screenshot size that fits the content.
+    ///
+    /// * `expected_file_name` - The file name of the snapshot.
+    /// * `max_size` - The dimensions in egui points used to measure the
+    ///   content. The screenshot will never be larger than this.
+    /// * `n` - Number of times the frame should be rendered before the
+    ///   content is measured and again before the screenshot is compared.
+    /// * `ui` - Closure that creates the user interface.
+    ///
+    /// The user interface is rendered once with `max_size` and the area used
+    /// by egui ([`egui::Context::used_rect`]) is measured. Then the user
+    /// interface is rendered again with exactly this size. Content that fills
+    /// all available space, like an [`egui::CentralPanel`], will always use
+    /// `max_size`, so use e.g. [`egui::Area`] or [`egui::Window`] instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the actual and expected screenshots are not the same or if
+    /// the given snapshot file to compare against does not exist.
+    pub fn assert_screenshot_auto_size(
+        &mut self,
+        expected_file_name: &str,
+        max_size: (i32, i32),
+        n: usize,
+        ui: impl FnMut(&egui::Context),
+    ) {
+        if let Err(e) = self.check_screenshot_auto_size(expected_file_name, max_size, n, ui) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Check that the rendered view is the same as the snapshot, using a
+    /// screenshot size that fits the content.
+    ///
+    /// This works like [`Self::assert_screenshot_auto_size`], but returns an
+    /// error instead of panicking.
+    pub fn check_screenshot_auto_size(
+        &mut self,
+        expected_file_name: &str,
+        max_size: (i32, i32),
+        n: usize,
+        mut ui: impl FnMut(&egui::Context),
+    ) -> Result<(), ScreenshotError> {
+        let output_size = self.measure_content_size(max_size, n, &mut ui)?;
+        self.check_screenshot_after_n_frames(expected_file_name, output_size, n, ui)
+    }
+
+    /// Render the user interface with the `max_size` and return the size in
+    /// points needed to show all content.
+    fn measure_content_size(
+        &mut self,
+        max_size: (i32, i32),
+        n: usize,
+        ui: impl FnMut(&egui::Context),
+    ) -> Result<(i32, i32), ScreenshotError> {
+        self.run_frames(max_size, n, ui);
+        let used_rect = self.backend.egui_ctx.used_rect();
+        let output_size = (
+            (used_rect.max.x.ceil() as i32).min(max_size.0),
+            (used_rect.max.y.ceil() as i32).min(max_size.1),
+        );
+        if output_size.0 <= 0 || output_size.1 <= 0 {
+            return Err(ScreenshotError::EmptyRegion);
+        }
+        Ok(output_size)
+    }
+}
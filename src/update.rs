@@ -0,0 +1,152 @@
+//! Decide which snapshot files are written when a test runs.
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{ScreenshotError, TestBackend};
+
+/// Environment variable that selects the [`UpdatePolicy`].
+pub(crate) const UPDATE_ENV_VAR: &str = "EGUI_SCREENSHOT_UPDATE";
+/// Environment variable that selects [`UpdatePolicy::All`], kept for
+/// compatibility with older versions.
+pub(crate) const REPLACE_ENV_VAR: &str = "EGUI_SCREENSHOT_REPLACE";
+
+/// Which snapshot files are written with the actual screenshot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpdatePolicy {
+    /// Never write snapshot files, a missing or different snapshot fails the
+    /// comparison.
+    #[default]
+    Never,
+    /// Create snapshot files that do not exist yet, but never change existing
+    /// ones.
+    Missing,
+    /// Create missing snapshot files and update the ones that do not match
+    /// the actual screenshot. Matching files are not touched.
+    Mismatching,
+    /// Overwrite all snapshot files, even the matching ones.
+    All,
+    /// Never change snapshot files, but write missing and mismatching
+    /// screenshots as pending snapshots (e.g. `main.new.png` for `main.png`)
+    /// next to the snapshot file. The comparison still fails. Pending
+    /// snapshots can be accepted or rejected with the `egui-screenshot-review`
+    /// binary.
+    Pending,
+}
+
+impl FromStr for UpdatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "never" => Ok(UpdatePolicy::Never),
+            "missing" => Ok(UpdatePolicy::Missing),
+            "mismatching" => Ok(UpdatePolicy::Mismatching),
+            "all" => Ok(UpdatePolicy::All),
+            "pending" => Ok(UpdatePolicy::Pending),
+            _ => Err(format!(
+                "Unknown update policy {:?}, expected one of \"never\", \"missing\", \
+                 \"mismatching\", \"all\" or \"pending\"",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for UpdatePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            UpdatePolicy::Never => "never",
+            UpdatePolicy::Missing => "missing",
+            UpdatePolicy::Mismatching => "mismatching",
+            UpdatePolicy::All => "all",
+            UpdatePolicy::Pending => "pending",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl UpdatePolicy {
+    /// Read the policy from the environment variables, if any is set.
+    pub(crate) fn from_env() -> Result<Option<UpdatePolicy>, ScreenshotError> {
+        if let Ok(value) = std::env::var(UPDATE_ENV_VAR) {
+            value
+                .parse()
+                .map(Some)
+                .map_err(|message| ScreenshotError::InvalidEnvVar {
+                    name: UPDATE_ENV_VAR.to_string(),
+                    message,
+                })
+        } else if std::env::var(REPLACE_ENV_VAR).is_ok() {
+            Ok(Some(UpdatePolicy::All))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Whether a snapshot file that does not exist yet is created.
+    pub(crate) fn creates_missing(&self) -> bool {
+        matches!(
+            self,
+            UpdatePolicy::Missing | UpdatePolicy::Mismatching | UpdatePolicy::All
+        )
+    }
+}
+
+impl TestBackend {
+    /// Set which snapshot files are written with the actual screenshot. Per
+    /// default, no snapshot files are written.
+    ///
+    /// The environment variable `EGUI_SCREENSHOT_UPDATE` with one of the
+    /// values `never`, `missing`, `mismatching`, `all` or `pending` overrides
+    /// this setting. Setting `EGUI_SCREENSHOT_REPLACE` to any value is the same as
+    /// `EGUI_SCREENSHOT_UPDATE=all`. Comparisons return
+    /// [`ScreenshotError::InvalidEnvVar`] if `EGUI_SCREENSHOT_UPDATE` has any
+    /// other value.
+    pub fn with_update_policy(mut self, policy: UpdatePolicy) -> Self {
+        self.update_policy = policy;
+        self
+    }
+
+    /// The policy that is used for the next comparison, taking the
+    /// environment variables into account.
+    pub(crate) fn effective_update_policy(&self) -> Result<UpdatePolicy, ScreenshotError> {
+        Ok(UpdatePolicy::from_env()?.unwrap_or(self.update_policy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_policy() {
+        assert_eq!(Ok(UpdatePolicy::Missing), "missing".parse());
+        assert_eq!(Ok(UpdatePolicy::Mismatching), "Mismatching".parse());
+        assert!("sometimes".parse::<UpdatePolicy>().is_err());
+        for policy in [
+            UpdatePolicy::Never,
+            UpdatePolicy::Missing,
+            UpdatePolicy::Mismatching,
+            UpdatePolicy::All,
+            UpdatePolicy::Pending,
+        ] {
+            assert_eq!(Ok(policy), policy.to_string().parse());
+        }
+    }
+
+    #[test]
+    fn invalid_policy_in_env_is_an_error() {
+        temp_env::with_var(UPDATE_ENV_VAR, Some("sometimes"), || {
+            assert!(matches!(
+                UpdatePolicy::from_env(),
+                Err(ScreenshotError::InvalidEnvVar { .. })
+            ));
+        });
+        temp_env::with_vars(
+            [(UPDATE_ENV_VAR, None), (REPLACE_ENV_VAR, Some("1"))],
+            || {
+                assert_eq!(Some(UpdatePolicy::All), UpdatePolicy::from_env().unwrap());
+            },
+        );
+    }
+}
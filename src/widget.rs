@@ -0,0 +1,77 @@
+//! Snapshots of a single widget, cropped to the area of the widget.
+use egui::{Rect, Vec2};
+
+use crate::{ScreenshotError, TestBackend};
+
+impl TestBackend {
+    /// Assert that a single widget is rendered the same as the snapshot.
+    ///
+    /// * `expected_file_name` - The file name of the snapshot.
+    /// * `output_size` - The dimensions of the rendered view in egui points.
+    /// * `n` - Number of times the frame should be rendered before the
+    ///   screenshot is compared.
+    /// * `padding` - Space in egui points around the widget that is included
+    ///   in the screenshot.
+    /// * `add_contents` - Closure that adds the widget to the given [`egui::Ui`]
+    ///   and returns its response.
+    ///
+    /// The screenshot is cropped to the rectangle of the returned
+    /// [`egui::Response`], so the snapshot does not change when the
+    /// surrounding layout changes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the actual and expected screenshots are not the same or if
+    /// the given snapshot file to compare against does not exist.
+    pub fn assert_widget_screenshot(
+        &mut self,
+        expected_file_name: &str,
+        output_size: (i32, i32),
+        n: usize,
+        padding: f32,
+        add_contents: impl FnMut(&mut egui::Ui) -> egui::Response,
+    ) {
+        if let Err(e) =
+            self.check_widget_screenshot(expected_file_name, output_size, n, padding, add_contents)
+        {
+            panic!("{}", e);
+        }
+    }
+
+    /// Check that a single widget is rendered the same as the snapshot.
+    ///
+    /// This works like [`Self::assert_widget_screenshot`], but returns an
+    /// error instead of panicking.
+    pub fn check_widget_screenshot(
+        &mut self,
+        expected_file_name: &str,
+        output_size: (i32, i32),
+        n: usize,
+        padding: f32,
+        mut add_contents: impl FnMut(&mut egui::Ui) -> egui::Response,
+    ) -> Result<(), ScreenshotError> {
+        let mut surface = self.create_surface(output_size)?;
+
+        let mut widget_rect = None;
+        self.run_frames(output_size, n, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                widget_rect = Some(add_contents(ui).rect);
+            });
+        });
+        self.paint(&mut surface);
+
+        let screen_rect = Rect::from_min_size(
+            egui::Pos2::ZERO,
+            Vec2::new(output_size.0 as f32, output_size.1 as f32),
+        );
+        let crop = widget_rect
+            .ok_or(ScreenshotError::EmptyRegion)?
+            .expand(padding)
+            .intersect(screen_rect);
+        if !crop.is_positive() {
+            return Err(ScreenshotError::EmptyRegion);
+        }
+
+        self.check_eq_screenshot(expected_file_name, &mut surface, Some(crop))
+    }
+}